@@ -0,0 +1,78 @@
+//! Times `find_value0`/`find_value1` lookups with literal and precompiled paths.
+//!
+//! Run with `cargo bench --bench find_value`.
+
+use std::hint::black_box;
+use std::time::Instant;
+use treexml_util::{parse_node, ElementExt, Path};
+
+const LOOKUPS: usize = 10_000;
+const PATH_LOOKUPS: usize = 100_000;
+const ROUNDS: usize = 20;
+
+fn main() {
+    let doc = format!(
+        "<reply>{}</reply>",
+        (0..100)
+            .map(|i| format!(
+                "<result><name>wu_{}</name><file_ref><size>{}</size></file_ref></result>",
+                i,
+                i * 10
+            ))
+            .collect::<String>()
+    );
+    let root = parse_node(&doc).unwrap().unwrap();
+
+    bench("find_value0 hit", || {
+        for _ in 0..LOOKUPS {
+            black_box(root.find_value0::<i64, _>("result.file_ref.size").unwrap());
+        }
+    });
+    bench("find_value0 miss", || {
+        for _ in 0..LOOKUPS {
+            black_box(root.find_value0::<i64, _>("result.file_ref.md5").unwrap());
+        }
+    });
+    bench("find_value1 hit", || {
+        for _ in 0..LOOKUPS {
+            black_box(root.find_value1::<i64, _>("result.file_ref.size").unwrap());
+        }
+    });
+
+    let results = root.children_named("result").collect::<Vec<_>>();
+    bench_n("str path", PATH_LOOKUPS, || {
+        for r in results.iter().cycle().take(PATH_LOOKUPS) {
+            black_box(r.find_value1::<i64, _>("file_ref.size").unwrap());
+        }
+    });
+    let path = Path::parse("file_ref.size").unwrap();
+    bench_n("precompiled path", PATH_LOOKUPS, || {
+        for r in results.iter().cycle().take(PATH_LOOKUPS) {
+            black_box(r.find_value1_path::<i64>(&path).unwrap());
+        }
+    });
+}
+
+fn bench<F>(name: &str, f: F)
+where
+    F: FnMut(),
+{
+    bench_n(name, LOOKUPS, f)
+}
+
+/// Prints the fastest of several rounds of `f`, which performs `lookups` lookups.
+fn bench_n<F>(name: &str, lookups: usize, mut f: F)
+where
+    F: FnMut(),
+{
+    let best = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+
+    println!("{:<20} {:>10.2?} per {} lookups", name, best, lookups);
+}
@@ -0,0 +1,54 @@
+//! Times lookups among many children with `ElementExt` and with `IndexedElement`.
+//!
+//! Run with `cargo bench --bench indexed`.
+
+use std::hint::black_box;
+use std::time::Instant;
+use treexml_util::{parse_node, ElementExt, IndexedElement};
+
+const CHILDREN: usize = 5_000;
+const LOOKUPS: usize = 10_000;
+const ROUNDS: usize = 5;
+
+fn main() {
+    let doc = format!(
+        "<state>{}</state>",
+        (0..CHILDREN)
+            .map(|i| format!("<entry_{0}><value>{0}</value></entry_{0}>", i))
+            .collect::<String>()
+    );
+    let root = parse_node(&doc).unwrap().unwrap();
+    let paths = (0..LOOKUPS)
+        .map(|i| format!("entry_{}.value", i * 7919 % CHILDREN))
+        .collect::<Vec<_>>();
+
+    bench("ElementExt", || {
+        for path in &paths {
+            black_box(root.find_value1::<u64, _>(path).unwrap());
+        }
+    });
+    bench("IndexedElement", || {
+        let indexed = IndexedElement::new(&root);
+        for path in &paths {
+            black_box(indexed.find_value1::<u64, _>(path).unwrap());
+        }
+    });
+}
+
+/// Prints the fastest of several rounds of `f`, which performs `LOOKUPS` lookups. The
+/// `IndexedElement` rounds include building the index.
+fn bench<F>(name: &str, mut f: F)
+where
+    F: FnMut(),
+{
+    let best = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+
+    println!("{:<15} {:>10.2?} per {} lookups", name, best, LOOKUPS);
+}
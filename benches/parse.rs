@@ -0,0 +1,56 @@
+//! Times parsing a generated 10 MB document with `treexml` and with the `quick-xml` backend.
+//!
+//! Run with `cargo bench --bench parse --features quick-xml`.
+
+use std::hint::black_box;
+use std::time::Instant;
+use treexml_util::parse_node;
+
+const SIZE: usize = 10 * 1024 * 1024;
+const ROUNDS: usize = 5;
+
+fn main() {
+    let doc = document();
+
+    bench("treexml", &doc, || {
+        black_box(treexml::Document::parse(doc.as_bytes()).unwrap());
+    });
+    bench("quick-xml", &doc, || {
+        black_box(parse_node(&doc).unwrap());
+    });
+}
+
+/// Builds a document of at least `SIZE` bytes mixing attributes, nested elements, entities and
+/// CDATA.
+fn document() -> String {
+    let mut doc = String::from("<?xml version=\"1.0\"?>\n<reply>\n");
+    let mut i = 0;
+    while doc.len() < SIZE {
+        doc.push_str(&format!(
+            "  <result id=\"{0}\" state=\"active\">\n    <name>wu_{0} &amp; co</name>\n    <file_ref><file_name>f_{0}.dat</file_name><size>{1}</size></file_ref>\n    <log><![CDATA[done <{0}>]]></log>\n    <suspended/>\n  </result>\n",
+            i,
+            i * 10
+        ));
+        i += 1;
+    }
+    doc.push_str("</reply>\n");
+    doc
+}
+
+/// Prints the fastest of several rounds of `f`, which parses `doc` once.
+fn bench<F>(name: &str, doc: &str, mut f: F)
+where
+    F: FnMut(),
+{
+    let best = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+
+    let mb_per_s = doc.len() as f64 / best.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<10} {:>10.2?} ({:.1} MB/s)", name, best, mb_per_s);
+}
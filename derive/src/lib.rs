@@ -0,0 +1,141 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{ext::IdentExt, parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Derives `treexml_util::Unmarshaller` for a struct with named fields.
+///
+/// Every field is unmarshalled from the first child element with the same name.
+/// Fields of type `Option<_>` and `bool`, as well as fields marked with `#[treexml(default)]`,
+/// are left untouched when the child is absent; any other missing field is an error.
+///
+/// Supported field attributes:
+/// - `#[treexml(rename = "name")]` reads the field from a child with a different name
+/// - `#[treexml(skip)]` never touches the field
+/// - `#[treexml(default)]` makes the field optional
+#[proc_macro_derive(Unmarshaller, attributes(treexml))]
+pub fn derive_unmarshaller(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(v) => v.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    skip: bool,
+    default: bool,
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut out = FieldAttrs::default();
+    for attr in &field.attrs {
+        if !attr.path().is_ident("treexml") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let v: LitStr = meta.value()?.parse()?;
+                out.rename = Some(v.value());
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                out.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                out.default = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported treexml attribute"))
+            }
+        })?;
+    }
+
+    Ok(out)
+}
+
+fn is_implicitly_optional(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) if p.qself.is_none() => p
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "Option" || s.ident == "bool")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Unmarshaller can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Unmarshaller can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut steps = Vec::new();
+    for field in fields {
+        let attrs = parse_field_attrs(field)?;
+        if attrs.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = ident.unraw().to_string();
+        let xml_name = attrs.rename.unwrap_or_else(|| field_name.clone());
+
+        let on_missing = if attrs.default || is_implicitly_optional(&field.ty) {
+            quote! {}
+        } else {
+            quote! {
+                return Err(::treexml_util::UtilError::MissingField {
+                    field: #field_name.into(),
+                    element: #xml_name.into(),
+                    parent: node.name.clone(),
+                });
+            }
+        };
+
+        steps.push(quote! {
+            match node.children.iter().find(|c| c.name == #xml_name) {
+                Some(child) => {
+                    ::treexml_util::Unmarshaller::unmarshal_from(&mut self.#ident, child)
+                        .map_err(|e| e.within(&node.name))?;
+                }
+                None => {
+                    #on_missing
+                }
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::treexml_util::Unmarshaller for #name #ty_generics #where_clause {
+            fn unmarshal_from(
+                &mut self,
+                node: &::treexml_util::__private::Element,
+            ) -> ::std::result::Result<bool, ::treexml_util::UtilError> {
+                #(#steps)*
+
+                Ok(true)
+            }
+        }
+    })
+}
@@ -0,0 +1,120 @@
+//! Canonical form for stable comparison and hashing.
+
+/// Controls which normalizations `canonicalize` applies beyond the ones it always performs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CanonicalizeOptions {
+    /// Sort children by name, then by their canonical serialization.
+    pub sort_children: bool,
+    /// Trim text and collapse internal runs of whitespace into a single space.
+    pub normalize_whitespace: bool,
+    /// Remove children left without text, cdata, attributes or children.
+    pub remove_empty: bool,
+}
+
+impl Default for CanonicalizeOptions {
+    fn default() -> Self {
+        Self {
+            sort_children: true,
+            normalize_whitespace: true,
+            remove_empty: false,
+        }
+    }
+}
+
+/// Rewrites `e` and its subtree into canonical form.
+///
+/// Empty text and cdata always become `None`. Attributes are kept in a `HashMap`, so their order
+/// is fixed by `canonical_string`, which writes them sorted by key.
+pub fn canonicalize(e: &mut treexml::Element, opts: &CanonicalizeOptions) {
+    if opts.normalize_whitespace {
+        if let Some(text) = &mut e.text {
+            *text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+    }
+    if e.text.as_deref() == Some("") {
+        e.text = None;
+    }
+    if e.cdata.as_deref() == Some("") {
+        e.cdata = None;
+    }
+
+    for child in &mut e.children {
+        canonicalize(child, opts);
+    }
+
+    if opts.remove_empty {
+        e.children.retain(|c| {
+            c.text.is_some()
+                || c.cdata.is_some()
+                || !c.attributes.is_empty()
+                || !c.children.is_empty()
+        });
+    }
+
+    if opts.sort_children {
+        e.children
+            .sort_by_cached_key(|c| (c.name.clone(), canonical_string(c)));
+    }
+}
+
+/// Serializes `e` without a declaration or indentation, with attributes sorted by key.
+///
+/// Two trees that are equal element-wise always produce the same string.
+pub fn canonical_string(e: &treexml::Element) -> String {
+    crate::write::serialize_element(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    fn canonical(s: &str, opts: CanonicalizeOptions) -> String {
+        let mut e = parse_node(s).unwrap().unwrap();
+        canonicalize(&mut e, &opts);
+
+        canonical_string(&e)
+    }
+
+    #[test]
+    fn test_canonicalize_reordered() {
+        let a = r#"<reply><result id="2" kind="x"><name>b   c</name></result><result kind="x" id="1"><name> a </name></result><status>ok</status></reply>"#;
+        let b = r#"<reply><status>ok</status><result id="1" kind="x"><name>a</name></result><result kind="x" id="2"><name>b c</name></result></reply>"#;
+        let expectation = r#"<reply><result id="1" kind="x"><name>a</name></result><result id="2" kind="x"><name>b c</name></result><status>ok</status></reply>"#;
+
+        let result_a = canonical(a, CanonicalizeOptions::default());
+        let result_b = canonical(b, CanonicalizeOptions::default());
+
+        assert_eq!(expectation, result_a);
+        assert_eq!(expectation, result_b);
+    }
+
+    #[test]
+    fn test_canonicalize_remove_empty() {
+        let opts = CanonicalizeOptions {
+            remove_empty: true,
+            ..Default::default()
+        };
+
+        let result = canonical(
+            r#"<a><b><![CDATA[]]></b><c><d/></c><e x="1"/><f>1</f></a>"#,
+            opts,
+        );
+
+        assert_eq!(r#"<a><e x="1"/><f>1</f></a>"#, result);
+    }
+
+    #[test]
+    fn test_canonical_string_escaping() {
+        let mut fixture = treexml::Element::new("a");
+        fixture.attributes.insert("q".into(), "\"<&>\"".into());
+        fixture.text = Some("1 < 2 & 3".into());
+
+        let result = canonical_string(&fixture);
+
+        assert_eq!(
+            r#"<a q="&quot;&lt;&amp;&gt;&quot;">1 &lt; 2 &amp; 3</a>"#,
+            result
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Structural comparison of element trees.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// Relaxations applied by `deep_eq`. The default compares everything strictly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EqOptions {
+    /// Compare children as a multiset instead of a sequence.
+    pub ignore_child_order: bool,
+    /// Trim text and cdata before comparing; whitespace-only content counts as absent.
+    pub trim_text: bool,
+    /// Skip attribute comparison entirely.
+    pub ignore_attributes: bool,
+    /// Compare text and cdata as a single value, so `<x>v</x>` equals `<x><![CDATA[v]]></x>`.
+    pub ignore_cdata_vs_text: bool,
+}
+
+/// Compares two trees recursively under the relaxations in `opts`.
+pub fn deep_eq(a: &treexml::Element, b: &treexml::Element, opts: &EqOptions) -> bool {
+    if a.name != b.name || a.prefix != b.prefix || a.children.len() != b.children.len() {
+        return false;
+    }
+
+    if !opts.ignore_attributes && a.attributes != b.attributes {
+        return false;
+    }
+
+    if opts.ignore_cdata_vs_text {
+        let joined = |e: &treexml::Element| match (&e.text, &e.cdata) {
+            (None, None) => None,
+            (text, cdata) => Some(format!(
+                "{}{}",
+                text.as_deref().unwrap_or_default(),
+                cdata.as_deref().unwrap_or_default()
+            )),
+        };
+        if !content_eq(joined(a).as_deref(), joined(b).as_deref(), opts) {
+            return false;
+        }
+    } else if !content_eq(a.text.as_deref(), b.text.as_deref(), opts)
+        || !content_eq(a.cdata.as_deref(), b.cdata.as_deref(), opts)
+    {
+        return false;
+    }
+
+    if opts.ignore_child_order {
+        let mut used = vec![false; b.children.len()];
+        a.children.iter().all(|x| {
+            match (0..b.children.len()).find(|&i| !used[i] && deep_eq(x, &b.children[i], opts)) {
+                Some(i) => {
+                    used[i] = true;
+                    true
+                }
+                None => false,
+            }
+        })
+    } else {
+        a.children
+            .iter()
+            .zip(&b.children)
+            .all(|(x, y)| deep_eq(x, y, opts))
+    }
+}
+
+fn content_eq(a: Option<&str>, b: Option<&str>, opts: &EqOptions) -> bool {
+    if !opts.trim_text {
+        return a == b;
+    }
+
+    fn normalize(v: Option<&str>) -> Option<&str> {
+        v.map(str::trim).filter(|v| !v.is_empty())
+    }
+    normalize(a) == normalize(b)
+}
+
+/// The way two trees differ at a given path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    /// The element exists only in the left tree.
+    MissingElement,
+    /// The element exists only in the right tree.
+    ExtraElement,
+    TextMismatch,
+    CdataMismatch,
+    AttributeMismatch {
+        name: String,
+    },
+}
+
+/// A single difference reported by `diff_elements`.
+///
+/// `path` uses the lookup path syntax relative to the compared roots and is empty for the roots
+/// themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ElementDiff {
+    pub path: String,
+    pub kind: DiffKind,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+impl fmt::Display for ElementDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+        let value = |v: &Option<String>| match v {
+            Some(v) => format!("{:?}", v),
+            None => "none".to_string(),
+        };
+
+        match &self.kind {
+            DiffKind::MissingElement => write!(f, "{}: missing element", path),
+            DiffKind::ExtraElement => write!(f, "{}: extra element", path),
+            DiffKind::TextMismatch => write!(
+                f,
+                "{}: text {} != {}",
+                path,
+                value(&self.left),
+                value(&self.right)
+            ),
+            DiffKind::CdataMismatch => write!(
+                f,
+                "{}: cdata {} != {}",
+                path,
+                value(&self.left),
+                value(&self.right)
+            ),
+            DiffKind::AttributeMismatch { name } => write!(
+                f,
+                "{}: attribute {} {} != {}",
+                path,
+                name,
+                value(&self.left),
+                value(&self.right)
+            ),
+        }
+    }
+}
+
+/// Lists every difference between two trees.
+///
+/// Children are paired by name and position among same-named siblings, so an inserted element
+/// shows up as a change to its later namesakes plus one extra element.
+pub fn diff_elements(a: &treexml::Element, b: &treexml::Element) -> Vec<ElementDiff> {
+    let mut out = Vec::new();
+    diff_into(&mut out, "", a, b);
+    out
+}
+
+fn diff_into(out: &mut Vec<ElementDiff>, path: &str, a: &treexml::Element, b: &treexml::Element) {
+    let report =
+        |out: &mut Vec<ElementDiff>, kind, left: &Option<String>, right: &Option<String>| {
+            if left != right {
+                out.push(ElementDiff {
+                    path: path.to_string(),
+                    kind,
+                    left: left.clone(),
+                    right: right.clone(),
+                });
+            }
+        };
+
+    report(out, DiffKind::TextMismatch, &a.text, &b.text);
+    report(out, DiffKind::CdataMismatch, &a.cdata, &b.cdata);
+
+    let names = a
+        .attributes
+        .keys()
+        .chain(b.attributes.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        report(
+            out,
+            DiffKind::AttributeMismatch { name: name.clone() },
+            &a.attributes.get(name).cloned(),
+            &b.attributes.get(name).cloned(),
+        );
+    }
+
+    let (count_a, count_b) = (count_names(a), count_names(b));
+    let child_path = |name: &str, n: usize| {
+        let repeated = count_a.get(name).copied().unwrap_or_default() > 1
+            || count_b.get(name).copied().unwrap_or_default() > 1;
+        let segment = if repeated {
+            format!("{}[{}]", name, n)
+        } else {
+            name.to_string()
+        };
+        if path.is_empty() {
+            segment
+        } else {
+            format!("{}.{}", path, segment)
+        }
+    };
+
+    let mut seen = HashMap::<&str, usize>::new();
+    for child in &a.children {
+        let n = seen.entry(&child.name).or_default();
+        let other = b.children.iter().filter(|c| c.name == child.name).nth(*n);
+        let child_path = child_path(&child.name, *n);
+        *n += 1;
+
+        match other {
+            Some(other) => diff_into(out, &child_path, child, other),
+            None => out.push(ElementDiff {
+                path: child_path,
+                kind: DiffKind::MissingElement,
+                left: None,
+                right: None,
+            }),
+        }
+    }
+
+    let mut seen = HashMap::<&str, usize>::new();
+    for child in &b.children {
+        let n = seen.entry(&child.name).or_default();
+        if *n
+            >= count_a
+                .get(child.name.as_str())
+                .copied()
+                .unwrap_or_default()
+        {
+            out.push(ElementDiff {
+                path: child_path(&child.name, *n),
+                kind: DiffKind::ExtraElement,
+                left: None,
+                right: None,
+            });
+        }
+        *n += 1;
+    }
+}
+
+fn count_names(e: &treexml::Element) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for c in &e.children {
+        *counts.entry(c.name.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    fn eq(a: &str, b: &str, opts: EqOptions) -> bool {
+        let a = parse_node(a).unwrap().unwrap();
+        let b = parse_node(b).unwrap().unwrap();
+
+        deep_eq(&a, &b, &opts)
+    }
+
+    #[test]
+    fn test_deep_eq_strict() {
+        let opts = EqOptions::default();
+
+        assert!(eq("<a><b>1</b><c/></a>", "<a><b>1</b><c/></a>", opts));
+        assert!(!eq("<a><b>1</b><c/></a>", "<a><b>2</b><c/></a>", opts));
+        assert!(!eq("<a><b>1</b><c/></a>", "<a><b>1</b></a>", opts));
+        assert!(!eq("<a><b>1</b><c/></a>", "<a><c/><b>1</b></a>", opts));
+    }
+
+    #[test]
+    fn test_deep_eq_ignore_child_order() {
+        let opts = EqOptions {
+            ignore_child_order: true,
+            ..Default::default()
+        };
+
+        assert!(eq(
+            "<a><b>1</b><b>2</b><c><d/><e/></c></a>",
+            "<a><c><e/><d/></c><b>2</b><b>1</b></a>",
+            opts
+        ));
+        assert!(!eq(
+            "<a><b>1</b><b>1</b></a>",
+            "<a><b>1</b><b>2</b></a>",
+            opts
+        ));
+    }
+
+    #[test]
+    fn test_deep_eq_trim_text() {
+        let opts = EqOptions {
+            trim_text: true,
+            ..Default::default()
+        };
+
+        assert!(eq("<a><b> 1 </b></a>", "<a><b>1</b></a>", opts));
+        assert!(eq("<a><![CDATA[ ]]></a>", "<a/>", opts));
+        assert!(!eq("<a><b> 1 </b></a>", "<a><b>1 1</b></a>", opts));
+    }
+
+    #[test]
+    fn test_deep_eq_ignore_attributes() {
+        let opts = EqOptions {
+            ignore_attributes: true,
+            ..Default::default()
+        };
+
+        assert!(eq(r#"<a x="1"><b y="2"/></a>"#, "<a><b/></a>", opts));
+        assert!(!eq(r#"<a x="1"><b y="2"/></a>"#, "<a><c/></a>", opts));
+        assert!(!eq(r#"<a x="1"/>"#, "<a/>", EqOptions::default()));
+    }
+
+    #[test]
+    fn test_deep_eq_ignore_cdata_vs_text() {
+        let opts = EqOptions {
+            ignore_cdata_vs_text: true,
+            ..Default::default()
+        };
+
+        assert!(eq("<x>v</x>", "<x><![CDATA[v]]></x>", opts));
+        assert!(!eq("<x>v</x>", "<x><![CDATA[w]]></x>", opts));
+        assert!(!eq(
+            "<x>v</x>",
+            "<x><![CDATA[v]]></x>",
+            EqOptions::default()
+        ));
+    }
+
+    #[test]
+    fn test_deep_eq_combined() {
+        let opts = EqOptions {
+            ignore_child_order: true,
+            trim_text: true,
+            ignore_attributes: true,
+            ignore_cdata_vs_text: true,
+        };
+
+        assert!(eq(
+            r#"<a id="1"><b> 1 </b><c><![CDATA[x]]></c></a>"#,
+            r#"<a id="2"><c>x</c><b>1</b></a>"#,
+            opts
+        ));
+    }
+
+    fn diff(a: &str, b: &str) -> Vec<String> {
+        let a = parse_node(a).unwrap().unwrap();
+        let b = parse_node(b).unwrap().unwrap();
+
+        diff_elements(&a, &b)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_equal() {
+        let fixture = "<reply><result><name>a</name></result></reply>";
+
+        assert!(diff(fixture, fixture).is_empty());
+    }
+
+    #[test]
+    fn test_diff_elements() {
+        let expectation = vec![
+            "result[2]: missing element".to_string(),
+            "status: extra element".to_string(),
+        ];
+
+        let result = diff(
+            "<reply><result/><result/><result/></reply>",
+            "<reply><result/><result/><status/></reply>",
+        );
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_diff_text_and_cdata() {
+        let expectation = vec![
+            r#"result[1].name: text "b" != "c""#.to_string(),
+            r#"note: cdata "x" != none"#.to_string(),
+        ];
+
+        let result = diff(
+            "<reply><result><name>a</name></result><result><name>b</name></result><note><![CDATA[x]]></note></reply>",
+            "<reply><result><name>a</name></result><result><name>c</name></result><note/></reply>",
+        );
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_diff_attributes() {
+        let expectation = vec![
+            ElementDiff {
+                path: "".into(),
+                kind: DiffKind::AttributeMismatch { name: "a".into() },
+                left: Some("1".into()),
+                right: Some("2".into()),
+            },
+            ElementDiff {
+                path: "".into(),
+                kind: DiffKind::AttributeMismatch { name: "b".into() },
+                left: None,
+                right: Some("3".into()),
+            },
+        ];
+
+        let a = parse_node(r#"<x a="1"/>"#).unwrap().unwrap();
+        let b = parse_node(r#"<x a="2" b="3"/>"#).unwrap().unwrap();
+        let result = diff_elements(&a, &b);
+
+        assert_eq!(expectation, result);
+        assert_eq!(r#"<root>: attribute a "1" != "2""#, result[0].to_string());
+    }
+}
@@ -0,0 +1,223 @@
+//! Navigation with access to parents and siblings, which `treexml::Element` does not keep.
+
+use crate::path;
+use std::rc::Rc;
+
+#[derive(Debug)]
+struct Node<'a> {
+    element: &'a treexml::Element,
+    parent: Option<usize>,
+    /// Position among the children of the parent.
+    position: usize,
+    children: Vec<usize>,
+}
+
+/// A position in a tree that can move to its children, parent and siblings.
+///
+/// `Cursor::new` indexes the whole tree once; moving around only clones a shared handle to the
+/// index.
+#[derive(Clone, Debug)]
+pub struct Cursor<'a> {
+    nodes: Rc<Vec<Node<'a>>>,
+    current: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Starts at `root`.
+    pub fn new(root: &'a treexml::Element) -> Self {
+        let mut nodes = Vec::new();
+        index(&mut nodes, root, None, 0);
+        Self {
+            nodes: Rc::new(nodes),
+            current: 0,
+        }
+    }
+
+    /// Returns the element the cursor is at.
+    pub fn element(&self) -> &'a treexml::Element {
+        self.node().element
+    }
+
+    /// Moves to the first child named `name`.
+    pub fn child(&self, name: &str) -> Option<Self> {
+        self.children().find(|c| c.element().name == name)
+    }
+
+    /// Iterates over the children in document order.
+    pub fn children(&self) -> impl Iterator<Item = Cursor<'a>> + '_ {
+        self.node().children.iter().map(move |&i| self.at(i))
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        self.node().parent.map(|i| self.at(i))
+    }
+
+    pub fn next_sibling(&self) -> Option<Self> {
+        self.sibling(1)
+    }
+
+    pub fn prev_sibling(&self) -> Option<Self> {
+        self.sibling(-1)
+    }
+
+    /// Returns the path from the root to this element in the `ElementExt` syntax, indexing a
+    /// segment only when its element has siblings of the same name, e.g. `result[1].name`.
+    /// The root's path is empty.
+    pub fn path(&self) -> String {
+        let mut segments = Vec::new();
+        let mut node = self.node();
+        while let Some(parent) = node.parent {
+            let name = &node.element.name;
+            let siblings = &self.nodes[parent].element.children;
+            let same_name = |c: &&treexml::Element| c.name == *name;
+            segments.push(if siblings.iter().filter(same_name).count() > 1 {
+                let n = siblings[..node.position].iter().filter(same_name).count();
+                format!("{}[{}]", path::escape(name), n)
+            } else {
+                path::escape(name)
+            });
+            node = &self.nodes[parent];
+        }
+
+        segments.reverse();
+        segments.join(".")
+    }
+
+    fn node(&self) -> &Node<'a> {
+        &self.nodes[self.current]
+    }
+
+    fn at(&self, current: usize) -> Self {
+        Self {
+            nodes: Rc::clone(&self.nodes),
+            current,
+        }
+    }
+
+    fn sibling(&self, offset: isize) -> Option<Self> {
+        let node = self.node();
+        let siblings = &self.nodes[node.parent?].children;
+        let position = node.position.checked_add_signed(offset)?;
+        siblings.get(position).map(|&i| self.at(i))
+    }
+}
+
+/// Appends `e` and its descendants to `nodes` in pre-order, returning the index of `e`.
+fn index<'a>(
+    nodes: &mut Vec<Node<'a>>,
+    e: &'a treexml::Element,
+    parent: Option<usize>,
+    position: usize,
+) -> usize {
+    let id = nodes.len();
+    nodes.push(Node {
+        element: e,
+        parent,
+        position,
+        children: Vec::with_capacity(e.children.len()),
+    });
+    for (i, child) in e.children.iter().enumerate() {
+        let child = index(nodes, child, Some(id), i);
+        nodes[id].children.push(child);
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_node, ElementExt, Path};
+
+    fn fixture() -> treexml::Element {
+        parse_node(
+            "<reply><project><name>a</name></project><result><name>r0</name><file_ref><file_name>f0</file_name><file_name>f1</file_name></file_ref></result><result><name>r1</name></result></reply>",
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cursor_down_and_up() {
+        let fixture = fixture();
+        let root = Cursor::new(&fixture);
+
+        let file = root
+            .child("result")
+            .and_then(|c| c.child("file_ref"))
+            .and_then(|c| c.child("file_name"))
+            .unwrap();
+        assert_eq!(Some("f0".to_string()), file.element().text);
+        assert_eq!("result[0].file_ref.file_name[0]", file.path());
+
+        let result = file.parent().and_then(|c| c.parent()).unwrap();
+        assert_eq!("result[0]", result.path());
+        assert_eq!(
+            vec!["name", "file_ref"],
+            result
+                .children()
+                .map(|c| c.element().name.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let root = result.parent().unwrap();
+        assert!(std::ptr::eq(&fixture, root.element()));
+        assert_eq!("", root.path());
+        assert!(root.parent().is_none());
+    }
+
+    #[test]
+    fn test_cursor_siblings() {
+        let fixture = fixture();
+        let project = Cursor::new(&fixture).child("project").unwrap();
+
+        assert!(project.prev_sibling().is_none());
+        let first = project.next_sibling().unwrap();
+        let second = first.next_sibling().unwrap();
+        assert!(second.next_sibling().is_none());
+        assert_eq!("result[1]", second.path());
+        assert_eq!(
+            "project",
+            second
+                .prev_sibling()
+                .unwrap()
+                .prev_sibling()
+                .unwrap()
+                .path()
+        );
+
+        let name = second.child("name").unwrap();
+        assert_eq!("result[1].name", name.path());
+        assert!(Cursor::new(&fixture).next_sibling().is_none());
+    }
+
+    #[test]
+    fn test_cursor_path_escapes() {
+        let fixture = parse_node("<reply><app.version><a/><a/></app.version></reply>")
+            .unwrap()
+            .unwrap();
+
+        let a = Cursor::new(&fixture)
+            .child("app.version")
+            .unwrap()
+            .child("a")
+            .unwrap();
+        assert_eq!(r"app\.version.a[0]", a.path());
+        assert!(std::ptr::eq(
+            a.element(),
+            fixture.find_path(&Path::parse(&a.path()).unwrap()).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_cursor_path_resolves() {
+        let fixture = fixture();
+        let root = Cursor::new(&fixture);
+
+        let mut stack = vec![root];
+        while let Some(c) = stack.pop() {
+            let path = Path::parse(&c.path()).unwrap();
+            assert!(std::ptr::eq(c.element(), fixture.find_path(&path).unwrap()));
+            stack.extend(c.children());
+        }
+    }
+}
@@ -0,0 +1,749 @@
+//! Deserialization of element trees with serde.
+
+use crate::{content, parse_bool_token, UtilError, FALSE_TOKENS, TRUE_TOKENS};
+use serde::de::value::StringDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Deserializes `T` from `e`.
+///
+/// Struct fields are read from the children of the same name, with repeated children forming a
+/// sequence, and from attributes under the field name prefixed with `@`, as in
+/// `#[serde(rename = "@id")]`. The text of an element is available as `$text`, or is read directly
+/// when the element is deserialized as a scalar or as the sole field of a newtype. Numbers ignore
+/// surrounding whitespace, booleans take the `find_bool` tokens, and an element without text reads
+/// as `true`.
+///
+/// Error paths start with the name of `e`, as for `Unmarshaller::unmarshal_from`.
+pub fn from_element<T>(e: &treexml::Element) -> Result<T, UtilError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(ElementDeserializer(e))
+}
+
+impl de::Error for UtilError {
+    fn custom<T: Display>(msg: T) -> Self {
+        UtilError::Custom {
+            path: String::new(),
+            message: msg.to_string(),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        UtilError::MissingField {
+            field: field.into(),
+            element: field.into(),
+            parent: String::new(),
+        }
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        UtilError::UnknownVariant {
+            path: String::new(),
+            value: variant.into(),
+            expected: expected.join(", "),
+        }
+    }
+}
+
+/// Reads a single element. Errors are prefixed with its name.
+struct ElementDeserializer<'de>(&'de treexml::Element);
+
+impl<'de> ElementDeserializer<'de> {
+    fn text(&self) -> Text<'de> {
+        Text(content(self.0).map(String::as_str))
+    }
+
+    fn within<T>(&self, r: Result<T, UtilError>) -> Result<T, UtilError> {
+        r.map_err(|e| e.within(&self.0.name))
+    }
+}
+
+macro_rules! forward_to_text {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, UtilError>
+            where
+                V: Visitor<'de>,
+            {
+                self.within(self.text().$method(visitor))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementDeserializer<'de> {
+    type Error = UtilError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.children.is_empty() && self.0.attributes.is_empty() {
+            match content(self.0) {
+                Some(text) => visitor.visit_borrowed_str(text),
+                None => visitor.visit_unit(),
+            }
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    forward_to_text! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Reads the children in order, indexing the ones that share a name with a sibling.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        let children = self.0.children.iter().collect::<Vec<_>>();
+        let indexed = children
+            .iter()
+            .map(|c| children.iter().filter(|s| s.name == c.name).count() > 1)
+            .collect::<Vec<_>>();
+        let seq = Seq {
+            elements: children,
+            indexed: Some(indexed),
+            pos: 0,
+        };
+        self.within(visitor.visit_seq(seq))
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.within(visitor.visit_map(Fields::new(self.0)))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.within(self.text().deserialize_enum(name, variants, visitor))
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// A value of a struct field: an attribute, the children of one name, or the text.
+enum Field<'de> {
+    Text(&'de str),
+    Children(&'de str, Vec<&'de treexml::Element>),
+}
+
+/// The attributes of an element, then its children grouped by name in order of first
+/// appearance, then its text.
+struct Fields<'de> {
+    entries: std::vec::IntoIter<(String, Field<'de>)>,
+    value: Option<Field<'de>>,
+}
+
+impl<'de> Fields<'de> {
+    fn new(e: &'de treexml::Element) -> Self {
+        let mut attributes = e.attributes.iter().collect::<Vec<_>>();
+        attributes.sort();
+        let mut entries = attributes
+            .into_iter()
+            .map(|(k, v)| (format!("@{}", k), Field::Text(v)))
+            .collect::<Vec<_>>();
+
+        let mut groups = Vec::<(&str, Vec<&treexml::Element>)>::new();
+        for child in &e.children {
+            match groups.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, group)) => group.push(child),
+                None => groups.push((&child.name, vec![child])),
+            }
+        }
+        entries.extend(
+            groups
+                .into_iter()
+                .map(|(name, group)| (name.to_string(), Field::Children(name, group))),
+        );
+
+        if let Some(text) = content(e) {
+            entries.push(("$text".into(), Field::Text(text)));
+        }
+
+        Self {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Fields<'de> {
+    type Error = UtilError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, UtilError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                self.value = Some(value);
+                let key: StringDeserializer<UtilError> = key.into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, UtilError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take().expect("value requested before its key") {
+            Field::Text(text) => seed.deserialize(Text(Some(text))),
+            Field::Children(name, elements) => seed.deserialize(Children { name, elements }),
+        }
+    }
+}
+
+/// Iterates over elements, each read by an `ElementDeserializer`.
+struct Seq<'de> {
+    elements: Vec<&'de treexml::Element>,
+    /// Which elements get an index in error paths; `None` indexes all of them.
+    indexed: Option<Vec<bool>>,
+    pos: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Seq<'de> {
+    type Error = UtilError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, UtilError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let e = match self.elements.get(self.pos) {
+            Some(e) => *e,
+            None => return Ok(None),
+        };
+        let i = self.pos;
+        self.pos += 1;
+
+        let indexed = self.indexed.as_ref().map_or(true, |indexed| indexed[i]);
+        let index = if indexed {
+            self.elements[..i]
+                .iter()
+                .filter(|prev| prev.name == e.name)
+                .count()
+        } else {
+            0
+        };
+        seed.deserialize(ElementDeserializer(e))
+            .map(Some)
+            .map_err(|err| {
+                if indexed {
+                    err.at_index(&e.name, index)
+                } else {
+                    err
+                }
+            })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len() - self.pos)
+    }
+}
+
+/// The children of one name, read as a sequence or, if there is only one, as that child.
+struct Children<'de> {
+    name: &'de str,
+    elements: Vec<&'de treexml::Element>,
+}
+
+impl<'de> Children<'de> {
+    fn single(self) -> Result<ElementDeserializer<'de>, UtilError> {
+        match self.elements[..] {
+            [e] => Ok(ElementDeserializer(e)),
+            _ => Err(UtilError::AmbiguousPath {
+                path: self.name.into(),
+                count: self.elements.len(),
+            }),
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, UtilError>
+            where
+                V: Visitor<'de>,
+            {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Children<'de> {
+    type Error = UtilError;
+
+    forward_to_single! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_map
+        deserialize_identifier
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.single()?.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Seq {
+            elements: self.elements,
+            indexed: None,
+            pos: 0,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// Text content, or `None` for an element without any. Errors have an empty path for the caller
+/// to fill in.
+struct Text<'de>(Option<&'de str>);
+
+impl<'de> Text<'de> {
+    fn parse<T>(&self) -> Result<T, UtilError>
+    where
+        T: FromStr,
+    {
+        let text = self.0.ok_or_else(|| UtilError::ValueNotFound {
+            path: String::new(),
+        })?;
+        text.trim().parse().map_err(|_| UtilError::InvalidValue {
+            path: String::new(),
+            value: text.into(),
+        })
+    }
+}
+
+macro_rules! parse_text {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, UtilError>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Text<'de> {
+    type Error = UtilError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.0.unwrap_or_default())
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            None => visitor.visit_bool(true),
+            Some(text) => match parse_bool_token(text, TRUE_TOKENS, FALSE_TOKENS) {
+                Some(v) => visitor.visit_bool(v),
+                None => Err(UtilError::InvalidBool {
+                    path: String::new(),
+                    token: text.into(),
+                }),
+            },
+        }
+    }
+
+    parse_text! {
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64, deserialize_i128 => visit_i128, deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16, deserialize_u32 => visit_u32, deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128, deserialize_f32 => visit_f32, deserialize_f64 => visit_f64
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        let text = self.0.unwrap_or_default();
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(UtilError::InvalidValue {
+                path: String::new(),
+                value: text.into(),
+            }),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, UtilError>
+    where
+        V: Visitor<'de>,
+    {
+        let text = self.0.ok_or_else(|| UtilError::ValueNotFound {
+            path: String::new(),
+        })?;
+        visitor.visit_enum(text.trim().into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Project {
+        #[serde(rename = "@id")]
+        id: u32,
+        name: String,
+        owner: Owner,
+        #[serde(default)]
+        task: Vec<Task>,
+        deadline: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Owner {
+        name: String,
+        active: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Task {
+        #[serde(rename = "@state")]
+        state: State,
+        #[serde(rename = "$text")]
+        title: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum State {
+        #[serde(rename = "open")]
+        Open,
+        #[serde(rename = "done")]
+        Done,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Score(i64);
+
+    #[test]
+    fn test_from_element() {
+        let fixture = parse_node(
+            r#"<project id=" 7"><name>Boinc</name><owner><name>vorot93</name><active/></owner><task state="open">Port</task><task state="done">Test</task></project>"#,
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = Project {
+            id: 7,
+            name: "Boinc".into(),
+            owner: Owner {
+                name: "vorot93".into(),
+                active: true,
+            },
+            task: vec![
+                Task {
+                    state: State::Open,
+                    title: "Port".into(),
+                },
+                Task {
+                    state: State::Done,
+                    title: "Test".into(),
+                },
+            ],
+            deadline: None,
+        };
+
+        let result = from_element::<Project>(&fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_from_element_option_and_single_child() {
+        let fixture = parse_node(
+            "<project id='1'><name/><owner><name>a</name><active> No </active></owner><task state='open'>x</task><deadline>2024</deadline></project>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = from_element::<Project>(&fixture).unwrap();
+
+        assert_eq!(Some("2024".to_string()), result.deadline);
+        assert_eq!(1, result.task.len());
+        assert_eq!("", result.name);
+        assert!(!result.owner.active);
+    }
+
+    #[test]
+    fn test_from_element_newtype() {
+        let fixture = parse_node("<score>\n  42\n</score>").unwrap().unwrap();
+
+        assert_eq!(Score(42), from_element::<Score>(&fixture).unwrap());
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Wide {
+        #[serde(rename = "@low")]
+        low: i128,
+        high: u128,
+    }
+
+    #[test]
+    fn test_from_element_wide_integers() {
+        let fixture = parse_node(
+            "<wide low='-170141183460469231731687303715884105728'><high> 340282366920938463463374607431768211455 </high></wide>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = Wide {
+            low: i128::MIN,
+            high: u128::MAX,
+        };
+
+        let result = from_element::<Wide>(&fixture).unwrap();
+
+        assert_eq!(expectation, result);
+
+        let fixture = parse_node("<wide low='0'><high>-1</high></wide>")
+            .unwrap()
+            .unwrap();
+        let e = from_element::<Wide>(&fixture).unwrap_err();
+        assert!(
+            matches!(e, UtilError::InvalidValue { path, value } if path == "wide.high" && value == "-1")
+        );
+    }
+
+    #[test]
+    fn test_from_element_errors() {
+        let fixture = parse_node(
+            "<project id='1'><name>a</name><owner><name>b</name><active>maybe</active></owner></project>",
+        )
+        .unwrap()
+        .unwrap();
+        let e = from_element::<Project>(&fixture).unwrap_err();
+        assert_eq!(
+            r#"invalid boolean "maybe" at project.owner.active"#,
+            e.to_string()
+        );
+
+        let fixture = parse_node(
+            "<project id='1'><name>a</name><owner><name>b</name><active/></owner><task state='open'>x</task><task state='late'>y</task></project>",
+        )
+        .unwrap()
+        .unwrap();
+        let e = from_element::<Project>(&fixture).unwrap_err();
+        assert_eq!(Some("project.task[1]"), e.path());
+
+        let fixture = parse_node("<project id='1'><name>a</name></project>")
+            .unwrap()
+            .unwrap();
+        let e = from_element::<Project>(&fixture).unwrap_err();
+        let expectation = "missing required field owner (element owner) at project";
+        assert_eq!(expectation, e.to_string());
+
+        let fixture = parse_node("<owner><name>a</name><name>b</name><active/></owner>")
+            .unwrap()
+            .unwrap();
+        let e = from_element::<Owner>(&fixture).unwrap_err();
+        assert_eq!("ambiguous path owner.name: 2 matches", e.to_string());
+    }
+}
@@ -0,0 +1,107 @@
+//! Transcoding of documents in legacy encodings to UTF-8.
+
+use crate::UtilError;
+
+/// Decodes `data` to UTF-8 based on its byte order mark or XML declaration.
+///
+/// UTF-8, US-ASCII, ISO-8859-1 and UTF-16 with a byte order mark are supported. The encoding
+/// named by the declaration is rewritten to `UTF-8` so it matches the returned text.
+pub(crate) fn decode_document(data: &[u8]) -> Result<String, UtilError> {
+    let text = if let Some(rest) = data.strip_prefix(b"\xEF\xBB\xBF") {
+        from_utf8(rest.to_vec())?
+    } else if let Some(rest) = data.strip_prefix(b"\xFF\xFE") {
+        decode_utf16(rest, u16::from_le_bytes)?
+    } else if let Some(rest) = data.strip_prefix(b"\xFE\xFF") {
+        decode_utf16(rest, u16::from_be_bytes)?
+    } else {
+        let head = &data[..data.len().min(256)];
+        let head = String::from_utf8_lossy(head);
+        let encoding =
+            declared_encoding(&head).map(|(start, end)| head[start..end].to_ascii_lowercase());
+
+        match encoding.as_deref() {
+            None | Some("utf-8") | Some("utf8") | Some("us-ascii") | Some("ascii") => {
+                from_utf8(data.to_vec())?
+            }
+            Some("iso-8859-1") | Some("iso8859-1") | Some("latin1") | Some("latin-1") => {
+                data.iter().map(|&b| char::from(b)).collect()
+            }
+            Some(other) => {
+                return Err(UtilError::UnsupportedEncoding {
+                    encoding: other.into(),
+                })
+            }
+        }
+    };
+
+    Ok(match declared_encoding(&text) {
+        Some((start, end)) => format!("{}UTF-8{}", &text[..start], &text[end..]),
+        None => text,
+    })
+}
+
+fn from_utf8(data: Vec<u8>) -> Result<String, UtilError> {
+    String::from_utf8(data).map_err(|e| UtilError::Decode {
+        encoding: "UTF-8".into(),
+        reason: e.to_string(),
+    })
+}
+
+fn decode_utf16(data: &[u8], f: fn([u8; 2]) -> u16) -> Result<String, UtilError> {
+    if data.len() % 2 != 0 {
+        return Err(UtilError::Decode {
+            encoding: "UTF-16".into(),
+            reason: "truncated".into(),
+        });
+    }
+
+    let units = data.chunks_exact(2).map(|c| f([c[0], c[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|e| UtilError::Decode {
+            encoding: "UTF-16".into(),
+            reason: e.to_string(),
+        })
+}
+
+/// Returns the byte range of the encoding name in the XML declaration at the start of `s`.
+fn declared_encoding(s: &str) -> Option<(usize, usize)> {
+    if !s.starts_with("<?xml") {
+        return None;
+    }
+
+    let decl = &s[..s.find("?>")?];
+    let after = decl.find("encoding")? + "encoding".len();
+    let rest = decl[after..].trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let start = decl.len() - rest.len() + 1;
+    let len = rest[1..].find(quote)?;
+
+    Some((start, start + len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_encoding() {
+        let fixture = r#"<?xml version="1.0" encoding = 'ISO-8859-1'?><a/>"#;
+
+        let result = declared_encoding(fixture).map(|(start, end)| &fixture[start..end]);
+
+        assert_eq!(Some("ISO-8859-1"), result);
+        assert_eq!(None, declared_encoding(r#"<?xml version="1.0"?><a/>"#));
+        assert_eq!(None, declared_encoding("<a/>"));
+    }
+
+    #[test]
+    fn test_decode_document_rewrites_declaration() {
+        let fixture = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>\xe9</a>";
+        let expectation = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><a>\u{e9}</a>";
+
+        let result = decode_document(fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+}
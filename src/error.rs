@@ -0,0 +1,284 @@
+//! Error type returned throughout the crate.
+
+use crate::LimitExceeded;
+use xml::common::Position;
+
+/// Everything that can go wrong while parsing, looking up or unmarshalling XML.
+///
+/// Paths use the `ElementExt` syntax and are relative to the element the failing call was made on.
+/// Errors from `Unmarshaller::unmarshal_from` start with the name of the element being
+/// unmarshalled, and containers extend them as they propagate, e.g.
+/// `result[17].file_ref.file_name`.
+#[derive(Debug, thiserror::Error)]
+pub enum UtilError {
+    #[error("XML error: {0}")]
+    Xml(#[from] treexml::Error),
+    #[error("XML error: {0}")]
+    XmlReader(#[from] xml::reader::Error),
+    /// A syntax error at a known position, counted from 1.
+    #[error("XML error at line {line}, column {column}")]
+    Syntax {
+        line: u64,
+        column: u64,
+        source: treexml::Error,
+    },
+    /// Parsing the document at `path` failed.
+    #[error("failed to parse {path}")]
+    Parse {
+        path: String,
+        #[source]
+        source: Box<UtilError>,
+    },
+    /// Opening or reading the file at `path` failed.
+    #[error("failed to read {path}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Writing serialized XML to a sink failed.
+    #[error("failed to write XML")]
+    Write {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("malformed document: {reason}")]
+    Malformed { reason: String },
+    #[error("unsupported document encoding: {encoding}")]
+    UnsupportedEncoding { encoding: String },
+    #[error("invalid {encoding} document: {reason}")]
+    Decode { encoding: String, reason: String },
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitExceeded),
+    #[error("invalid path {path}: {reason}")]
+    InvalidPath { path: String, reason: String },
+    #[error("value not found at {path}")]
+    ValueNotFound { path: String },
+    /// None of several alternative paths has a value.
+    #[error("value not found at any of {}", .paths.join(", "))]
+    ValuesNotFound { paths: Vec<String> },
+    #[error("element not found at {path}")]
+    ElementNotFound { path: String },
+    /// A lookup that requires a single match found several.
+    #[error("ambiguous path {path}: {count} matches")]
+    AmbiguousPath { path: String, count: usize },
+    #[error("attribute {name} not found at {path}")]
+    AttributeNotFound { path: String, name: String },
+    #[error("CDATA not found at {path}")]
+    CdataNotFound { path: String },
+    /// The content at `path` could not be converted to the requested type.
+    #[error("invalid value {value:?} at {path}")]
+    InvalidValue { path: String, value: String },
+    /// The content at `path` is not an absolute URL. `reason` tells relative URLs apart.
+    #[cfg(feature = "url")]
+    #[error("invalid URL {value:?} at {path}: {reason}")]
+    InvalidUrl {
+        path: String,
+        value: String,
+        reason: url::ParseError,
+    },
+    /// The content at `path` is zero where a non-zero number is required.
+    #[error("zero value {value:?} at {path}, expected a non-zero number")]
+    ZeroValue { path: String, value: String },
+    #[error("invalid value {:?} for attribute {}{}", .value, .name, at(.path))]
+    InvalidAttribute {
+        path: String,
+        name: String,
+        value: String,
+    },
+    #[error("invalid hex value {value:?} at {path}: {source}")]
+    InvalidHex {
+        path: String,
+        value: String,
+        source: HexError,
+    },
+    #[error("invalid boolean {token:?} at {path}")]
+    InvalidBool { path: String, token: String },
+    /// A flag looked up strictly had child elements or CDATA content.
+    #[error("unexpected content in flag at {path}")]
+    InvalidFlag { path: String },
+    /// A token was given as both a true and a false spelling.
+    #[error("ambiguous boolean token: {token}")]
+    AmbiguousBoolToken { token: String },
+    #[error("invalid value {value:?} at {path} (expected one of: {expected})")]
+    UnknownVariant {
+        path: String,
+        value: String,
+        expected: String,
+    },
+    #[error("missing required field {field} (element {element}) at {parent}")]
+    MissingField {
+        field: String,
+        element: String,
+        parent: String,
+    },
+    #[error("failed to unmarshal field {field}")]
+    Field {
+        field: String,
+        #[source]
+        source: Box<UtilError>,
+    },
+    /// An element held the wrong number of values or children.
+    #[error("expected {expected} items at {element}, found {found}")]
+    WrongCount {
+        element: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("duplicate key {key} at {element}")]
+    DuplicateKey { element: String, key: String },
+    #[error("nested elements are not allowed in map entry {element}")]
+    NestedMapEntry { element: String },
+    #[error("child index {index} out of range at {path} ({len} children)")]
+    IndexOutOfRange {
+        path: String,
+        index: usize,
+        len: usize,
+    },
+    #[error("invalid element name: {name:?}")]
+    InvalidName { name: String },
+    #[error("invalid CDATA content of {element}: {reason}")]
+    InvalidCdata { element: String, reason: String },
+    /// Failure reported by a serde `Serialize` or `Deserialize` impl.
+    #[error("{message} at {path}")]
+    Custom { path: String, message: String },
+    /// Failure reported by a hand-written `Unmarshaller`.
+    #[error(transparent)]
+    Other(#[from] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Why hexadecimal text could not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum HexError {
+    /// The digits, not counting a `0x` prefix, do not pair up into bytes.
+    #[error("odd number of hex digits: {len}")]
+    OddLength { len: usize },
+    /// `character` at `position`, counted from 0 including any `0x` prefix, is not a hex digit.
+    #[error("invalid hex character {character:?} at position {position}")]
+    InvalidCharacter { character: char, position: usize },
+}
+
+impl UtilError {
+    /// Returns the line of a syntax error, counted from 1.
+    pub fn line(&self) -> Option<u64> {
+        self.position().map(|(line, _)| line)
+    }
+
+    /// Returns the column of a syntax error, counted from 1.
+    pub fn column(&self) -> Option<u64> {
+        self.position().map(|(_, column)| column)
+    }
+
+    fn position(&self) -> Option<(u64, u64)> {
+        match self {
+            UtilError::Syntax { line, column, .. } => Some((*line, *column)),
+            UtilError::XmlReader(e) => {
+                let p = e.position();
+                Some((p.row + 1, p.column + 1))
+            }
+            UtilError::Parse { source, .. } => source.position(),
+            _ => None,
+        }
+    }
+
+    /// Returns the path of the element the error is about, if it has one.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            UtilError::Field { source, .. } => source.path(),
+            UtilError::ValuesNotFound { paths } => paths.first().map(String::as_str),
+            UtilError::ValueNotFound { path }
+            | UtilError::ElementNotFound { path }
+            | UtilError::AmbiguousPath { path, .. }
+            | UtilError::AttributeNotFound { path, .. }
+            | UtilError::CdataNotFound { path }
+            | UtilError::InvalidValue { path, .. }
+            | UtilError::ZeroValue { path, .. }
+            | UtilError::InvalidAttribute { path, .. }
+            | UtilError::InvalidHex { path, .. }
+            | UtilError::InvalidBool { path, .. }
+            | UtilError::InvalidFlag { path }
+            | UtilError::UnknownVariant { path, .. }
+            | UtilError::IndexOutOfRange { path, .. }
+            | UtilError::MissingField { parent: path, .. }
+            | UtilError::WrongCount { element: path, .. }
+            | UtilError::DuplicateKey { element: path, .. }
+            | UtilError::NestedMapEntry { element: path }
+            | UtilError::Custom { path, .. } => Some(path),
+            #[cfg(feature = "url")]
+            UtilError::InvalidUrl { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    fn path_mut(&mut self) -> Option<&mut String> {
+        match self {
+            UtilError::Field { source, .. } => source.path_mut(),
+            UtilError::ValueNotFound { path }
+            | UtilError::ElementNotFound { path }
+            | UtilError::AmbiguousPath { path, .. }
+            | UtilError::AttributeNotFound { path, .. }
+            | UtilError::CdataNotFound { path }
+            | UtilError::InvalidValue { path, .. }
+            | UtilError::ZeroValue { path, .. }
+            | UtilError::InvalidAttribute { path, .. }
+            | UtilError::InvalidHex { path, .. }
+            | UtilError::InvalidBool { path, .. }
+            | UtilError::InvalidFlag { path }
+            | UtilError::UnknownVariant { path, .. }
+            | UtilError::IndexOutOfRange { path, .. }
+            | UtilError::MissingField { parent: path, .. }
+            | UtilError::WrongCount { element: path, .. }
+            | UtilError::DuplicateKey { element: path, .. }
+            | UtilError::NestedMapEntry { element: path }
+            | UtilError::Custom { path, .. } => Some(path),
+            #[cfg(feature = "url")]
+            UtilError::InvalidUrl { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Prepends `segment` to the path, for errors raised by a child of the element `segment` names.
+    ///
+    /// Hand-written `Unmarshaller` impls that descend into children use this with their node's
+    /// name.
+    pub fn within(mut self, segment: &str) -> Self {
+        let prefix = |path: &mut String| {
+            *path = if path.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{}.{}", segment, path)
+            };
+        };
+        match &mut self {
+            UtilError::ValuesNotFound { paths } => paths.iter_mut().for_each(prefix),
+            _ => {
+                if let Some(path) = self.path_mut() {
+                    prefix(path);
+                }
+            }
+        }
+        self
+    }
+
+    /// Adds `[index]` to a path starting with the element `name`, for errors raised by one of
+    /// several siblings of that name.
+    pub(crate) fn at_index(mut self, name: &str, index: usize) -> Self {
+        if let Some(path) = self.path_mut() {
+            if let Some(rest) = path.strip_prefix(name) {
+                if rest.is_empty() || rest.starts_with('.') {
+                    *path = format!("{}[{}]{}", name, index, rest);
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Formats ` at {path}`, or nothing for the empty path of the element a call was made on.
+fn at(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" at {}", path)
+    }
+}
@@ -0,0 +1,188 @@
+//! Conversion between element trees and lists of path/value pairs.
+//!
+//! Each element with content maps to its path and its text, falling back to CDATA content, which
+//! comes back as text. Each attribute maps to the path of its element followed by `@name`. Empty
+//! elements map to an empty value so that they are kept. Paths use the `ElementExt` syntax relative
+//! to the root, with an index on segments whose element has siblings of the same name, so the
+//! root's own text is under the empty path and its attributes under `@name`.
+//!
+//! Text alongside children is kept, but its position among them is not.
+
+use crate::path::{self, Selector};
+use crate::visit::{walk, VisitAction, Visitor};
+use crate::{content, write, UtilError};
+use std::convert::Infallible;
+
+/// Lists the values in `e` as described in the module docs, in document order with the attributes
+/// of each element sorted by name, before its text.
+pub fn flatten(e: &treexml::Element) -> Vec<(String, String)> {
+    let mut flattener = Flattener(Vec::new());
+    walk(e, &mut flattener).unwrap_or_else(|never| match never {});
+    flattener.0
+}
+
+struct Flattener(Vec<(String, String)>);
+
+impl Visitor<Infallible> for Flattener {
+    fn enter(&mut self, path: &str, e: &treexml::Element) -> Result<VisitAction, Infallible> {
+        let mut attrs = e.attributes.iter().collect::<Vec<_>>();
+        attrs.sort();
+        for (name, value) in attrs {
+            self.0.push((format!("{}@{}", path, name), value.clone()));
+        }
+
+        if let Some(text) = content(e) {
+            self.0.push((path.into(), text.clone()));
+        } else if e.attributes.is_empty() && e.children.is_empty() && !path.is_empty() {
+            self.0.push((path.into(), String::new()));
+        }
+
+        Ok(VisitAction::Continue)
+    }
+}
+
+/// Builds an element named `name` from `pairs`, the inverse of `flatten`.
+///
+/// Elements are created in the order their paths first appear, and an empty value leaves an
+/// element without text. Fails with `InvalidPath` on paths that use wildcards or attribute
+/// predicates, or whose index skips over siblings not created yet, and with `InvalidName` on names
+/// that are not valid XML names.
+pub fn unflatten(name: &str, pairs: &[(String, String)]) -> Result<treexml::Element, UtilError> {
+    if !write::is_xml_name(name) {
+        return Err(UtilError::InvalidName { name: name.into() });
+    }
+
+    let mut root = treexml::Element::new(name);
+    for (key, value) in pairs {
+        let (path, attr) = match key.rsplit_once('@').filter(|(_, attr)| !attr.contains(']')) {
+            Some((path, attr)) => (path, Some(attr)),
+            None => (key.as_str(), None),
+        };
+
+        let mut e = &mut root;
+        for segment in path::parse(path)? {
+            let n = match segment.selectors.as_slice() {
+                _ if segment.name == "*" => {
+                    return Err(invalid_path(key, "wildcards are not allowed"))
+                }
+                [] => 0,
+                [Selector::Index(n)] => *n,
+                _ => return Err(invalid_path(key, "only indexes are allowed")),
+            };
+            if !write::is_xml_name(&segment.name) {
+                return Err(UtilError::InvalidName { name: segment.name });
+            }
+            e = child(e, &segment.name, n).ok_or_else(|| invalid_path(key, "index skips ahead"))?;
+        }
+
+        match attr {
+            Some(attr) => {
+                if !write::is_xml_name(attr) {
+                    return Err(UtilError::InvalidName { name: attr.into() });
+                }
+                e.attributes.insert(attr.into(), value.clone());
+            }
+            None if value.is_empty() => {}
+            None => e.text = Some(value.clone()),
+        }
+    }
+
+    Ok(root)
+}
+
+/// Returns the `n`-th child of `e` named `name`, appending it if `e` has exactly `n` such
+/// children.
+fn child<'a>(
+    e: &'a mut treexml::Element,
+    name: &str,
+    n: usize,
+) -> Option<&'a mut treexml::Element> {
+    let count = e.children.iter().filter(|c| c.name == name).count();
+    if n == count {
+        e.children.push(treexml::Element::new(name));
+        return e.children.last_mut();
+    }
+
+    e.children.iter_mut().filter(|c| c.name == name).nth(n)
+}
+
+fn invalid_path(path: &str, reason: &str) -> UtilError {
+    UtilError::InvalidPath {
+        path: path.into(),
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_node, serialize_element};
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flatten() {
+        let fixture = parse_node(
+            r#"<project id="1"><name>p</name><result state="done" id="a"><name>r0</name></result><result><name>r1</name><log><![CDATA[x]]></log></result><suspended/></project>"#,
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = pairs(&[
+            ("@id", "1"),
+            ("name", "p"),
+            ("result[0]@id", "a"),
+            ("result[0]@state", "done"),
+            ("result[0].name", "r0"),
+            ("result[1].name", "r1"),
+            ("result[1].log", "x"),
+            ("suspended", ""),
+        ]);
+
+        let result = flatten(&fixture);
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_flatten_round_trip() {
+        let fixture = parse_node(
+            r#"<reply v="2"><result><name>a</name><file><size>1</size></file><file><size>2</size></file></result><app x="y"/><result><name>b</name><flag/></result></reply>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = unflatten("reply", &flatten(&fixture)).unwrap();
+
+        assert_eq!(fixture, result);
+        assert_eq!(
+            "<e>t</e>",
+            serialize_element(&unflatten("e", &pairs(&[("", "t")])).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_unflatten_errors() {
+        let e = unflatten("a", &pairs(&[("b[1]", "x")])).unwrap_err();
+        assert!(
+            matches!(e, UtilError::InvalidPath { path, reason } if path == "b[1]" && reason == "index skips ahead")
+        );
+
+        let e = unflatten("a", &pairs(&[("*.c", "x")])).unwrap_err();
+        assert!(
+            matches!(e, UtilError::InvalidPath { reason, .. } if reason == "wildcards are not allowed")
+        );
+
+        let e = unflatten("a", &pairs(&[("b[@k=\"v\"]@x", "x")])).unwrap_err();
+        assert!(
+            matches!(e, UtilError::InvalidPath { reason, .. } if reason == "only indexes are allowed")
+        );
+
+        assert!(unflatten("a", &pairs(&[("b..c", "x")])).is_err());
+        assert!(unflatten("a", &pairs(&[("b@1x", "x")])).is_err());
+    }
+}
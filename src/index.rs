@@ -0,0 +1,220 @@
+//! Lookups backed by a per-element index of children by name.
+
+use crate::path::{self, Segment};
+use crate::{bool_token, parse_content, UtilError, FALSE_TOKENS, TRUE_TOKENS};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Positions of the children of one element, grouped by name.
+type ChildIndex<'a> = HashMap<&'a str, Vec<usize>>;
+
+/// A borrowed tree answering the `ElementExt` lookups without scanning children by name.
+///
+/// The index of an element's children is built the first time a lookup passes through it, so the
+/// first lookup costs a scan and later ones resolve each named segment directly. The tree stays
+/// borrowed for as long as the index exists, which keeps it from going stale.
+#[derive(Debug)]
+pub struct IndexedElement<'a> {
+    root: &'a treexml::Element,
+    index: RefCell<HashMap<*const treexml::Element, Rc<ChildIndex<'a>>>>,
+}
+
+impl<'a> IndexedElement<'a> {
+    pub fn new(root: &'a treexml::Element) -> Self {
+        Self {
+            root,
+            index: RefCell::default(),
+        }
+    }
+
+    /// Returns the wrapped element.
+    pub fn element(&self) -> &'a treexml::Element {
+        self.root
+    }
+
+    /// Same as `ElementExt::find_value0`.
+    pub fn find_value0<T, PATH>(&self, path: PATH) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        parse_content(self.find_element(path)?, path)
+    }
+
+    /// Same as `ElementExt::find_value1`.
+    pub fn find_value1<T, PATH>(&self, path: PATH) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        self.find_value0(path)
+            .and_then(|v| v.ok_or_else(|| UtilError::ValueNotFound { path: path.into() }))
+    }
+
+    /// Same as `ElementExt::find_bool`.
+    pub fn find_bool<PATH>(&self, path: PATH) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        bool_token(
+            self.find_element(path)?,
+            path,
+            TRUE_TOKENS,
+            FALSE_TOKENS,
+            false,
+        )
+        .map(|v| v.unwrap_or(false))
+    }
+
+    /// Same as `ElementExt::children_named`.
+    pub fn children_named(&self, name: &str) -> impl Iterator<Item = &'a treexml::Element> {
+        let root = self.root;
+        let positions = self.children_of(root).get(name).cloned();
+        positions
+            .into_iter()
+            .flatten()
+            .map(move |i| &root.children[i])
+    }
+
+    fn find_element(&self, path: &str) -> Result<Option<&'a treexml::Element>, UtilError> {
+        Ok(self.resolve_first(self.root, &path::parse(path)?))
+    }
+
+    /// Returns the first element matching `segments`, in document order, like
+    /// `path::resolve_first`.
+    fn resolve_first(
+        &self,
+        e: &'a treexml::Element,
+        segments: &[Segment],
+    ) -> Option<&'a treexml::Element> {
+        let (segment, rest) = match segments.split_first() {
+            Some(v) => v,
+            None => return Some(e),
+        };
+
+        if segment.name == "*" {
+            return segment
+                .select_indices(e)
+                .into_iter()
+                .find_map(|i| self.resolve_first(&e.children[i], rest));
+        }
+
+        let children = self.children_of(e);
+        let positions = children.get(segment.name.as_str())?;
+        if segment.selectors.is_empty() {
+            positions
+                .iter()
+                .find_map(|&i| self.resolve_first(&e.children[i], rest))
+        } else {
+            segment
+                .filter_indices(e, positions)
+                .into_iter()
+                .find_map(|i| self.resolve_first(&e.children[i], rest))
+        }
+    }
+
+    fn children_of(&self, e: &'a treexml::Element) -> Rc<ChildIndex<'a>> {
+        let key = e as *const treexml::Element;
+        if let Some(children) = self.index.borrow().get(&key) {
+            return Rc::clone(children);
+        }
+
+        let mut children = ChildIndex::new();
+        for (i, c) in e.children.iter().enumerate() {
+            children.entry(c.name.as_str()).or_default().push(i);
+        }
+        let children = Rc::new(children);
+        self.index.borrow_mut().insert(key, Rc::clone(&children));
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_node, ElementExt};
+
+    const PATHS: &[&str] = &[
+        "",
+        "name",
+        "result",
+        "result.name",
+        "result[1].name",
+        "result[9].name",
+        "result.file_ref.size",
+        "result[@state=\"done\"].name",
+        "result[@state=\"done\"][1].name",
+        "*.name",
+        "result.*[1]",
+        "missing",
+        "result.missing",
+        "result..name",
+        "result[x]",
+    ];
+
+    fn fixture() -> treexml::Element {
+        parse_node(
+            r#"<reply><name>r</name><result state="done"><name>a</name></result><result><name>b</name><file_ref><size>5</size></file_ref></result><result state="done"><name>c</name><flag/></result></reply>"#,
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    fn same<T>(expectation: Result<T, UtilError>, result: Result<T, UtilError>) -> bool
+    where
+        T: PartialEq,
+    {
+        match (expectation, result) {
+            (Ok(a), Ok(b)) => a == b,
+            (Err(a), Err(b)) => a.to_string() == b.to_string(),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_indexed_find_value_matches_element_ext() {
+        let fixture = fixture();
+        let indexed = IndexedElement::new(&fixture);
+
+        for path in PATHS {
+            assert!(
+                same(
+                    fixture.find_value0::<String, _>(path),
+                    indexed.find_value0::<String, _>(path)
+                ),
+                "{}",
+                path
+            );
+            assert!(
+                same(
+                    fixture.find_value1::<String, _>(path),
+                    indexed.find_value1::<String, _>(path)
+                ),
+                "{}",
+                path
+            );
+            assert!(
+                same(fixture.find_bool(path), indexed.find_bool(path)),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_indexed_children_named() {
+        let fixture = fixture();
+        let indexed = IndexedElement::new(&fixture);
+
+        for name in ["result", "name", "missing"] {
+            let expectation = fixture.children_named(name).collect::<Vec<_>>();
+            let result = indexed.children_named(name).collect::<Vec<_>>();
+            assert_eq!(expectation, result);
+        }
+        assert!(std::ptr::eq(&fixture, indexed.element()));
+    }
+}
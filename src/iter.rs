@@ -0,0 +1,66 @@
+//! Tree traversal iterators.
+
+/// Lazy pre-order traversal of an element and all of its descendants, yielding each element with
+/// its depth.
+///
+/// The root has depth 0. Only the path from the root to the current element is kept in memory.
+pub struct Descendants<'a> {
+    root: Option<&'a treexml::Element>,
+    stack: Vec<std::slice::Iter<'a, treexml::Element>>,
+}
+
+impl<'a> Descendants<'a> {
+    pub fn new(root: &'a treexml::Element) -> Self {
+        Self {
+            root: Some(root),
+            stack: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = (usize, &'a treexml::Element);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            self.stack.push(root.children.iter());
+            return Some((0, root));
+        }
+
+        loop {
+            let depth = self.stack.len();
+            match self.stack.last_mut()?.next() {
+                Some(e) => {
+                    self.stack.push(e.children.iter());
+                    return Some((depth, e));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_tree_element;
+
+    #[test]
+    fn test_descendants_lazy() {
+        let mut fixture = make_tree_element("leaf", vec![]);
+        for _ in 0..1_000 {
+            fixture = make_tree_element("node", vec![fixture]);
+        }
+
+        let mut it = Descendants::new(&fixture);
+        assert_eq!(
+            Some((0, "node")),
+            it.next().map(|(d, e)| (d, e.name.as_str()))
+        );
+        assert_eq!(1, it.stack.len());
+
+        assert_eq!(1_001, Descendants::new(&fixture).count());
+    }
+}
@@ -0,0 +1,235 @@
+//! Conversion between element trees and `serde_json` values.
+//!
+//! An element maps to `null` if it is empty and to a string if it only holds text. Otherwise it
+//! maps to an object holding its attributes under `"@attrs"`, its text under `"#text"` and each
+//! child under the child's name, with children that share a name collected into an array.
+//! Text falls back to CDATA content, which comes back as text.
+//!
+//! `serde_json` objects are sorted by key unless its `preserve_order` feature is enabled, so the
+//! order of children with different names is not kept. Whitespace-only text between children is
+//! kept as `"#text"`; strip it first to leave it out.
+
+use crate::{content, write, UtilError};
+use serde_json::{Map, Number, Value};
+
+/// Controls the output of `element_to_json_with`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Turn text and attribute values into JSON numbers or booleans when they read back to the
+    /// same string, e.g. `42` and `true` but not `042`.
+    pub coerce_scalars: bool,
+}
+
+/// Converts `e` to JSON, keeping all values as strings.
+pub fn element_to_json(e: &treexml::Element) -> Value {
+    element_to_json_with(e, &JsonOptions::default())
+}
+
+/// Converts `e` to JSON as described in the module docs.
+pub fn element_to_json_with(e: &treexml::Element, opts: &JsonOptions) -> Value {
+    if e.attributes.is_empty() && e.children.is_empty() {
+        return content(e).map_or(Value::Null, |text| scalar(text, opts));
+    }
+
+    let mut map = Map::new();
+    if !e.attributes.is_empty() {
+        let attrs = e
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.clone(), scalar(v, opts)))
+            .collect();
+        map.insert("@attrs".into(), Value::Object(attrs));
+    }
+
+    let mut groups = Vec::<(&str, Vec<Value>)>::new();
+    for child in &e.children {
+        let v = element_to_json_with(child, opts);
+        match groups.iter_mut().find(|(name, _)| *name == child.name) {
+            Some((_, group)) => group.push(v),
+            None => groups.push((&child.name, vec![v])),
+        }
+    }
+    for (name, mut group) in groups {
+        let v = if group.len() == 1 {
+            group.remove(0)
+        } else {
+            Value::Array(group)
+        };
+        map.insert(name.into(), v);
+    }
+
+    if let Some(text) = content(e) {
+        map.insert("#text".into(), scalar(text, opts));
+    }
+
+    Value::Object(map)
+}
+
+fn scalar(text: &str, opts: &JsonOptions) -> Value {
+    if opts.coerce_scalars {
+        if let Ok(b) = text.parse::<bool>() {
+            return Value::Bool(b);
+        }
+        let n = if let Ok(i) = text.parse::<i64>() {
+            Some(Number::from(i))
+        } else if let Ok(u) = text.parse::<u64>() {
+            Some(Number::from(u))
+        } else {
+            text.parse::<f64>().ok().and_then(Number::from_f64)
+        };
+        if let Some(n) = n.filter(|n| n.to_string() == text) {
+            return Value::Number(n);
+        }
+    }
+    Value::String(text.into())
+}
+
+/// Builds an element named `name` from `v`, the inverse of `element_to_json`.
+///
+/// Numbers and booleans become text. Fails on arrays that are not the value of a child, on
+/// `"@attrs"` that is not an object of scalars, on `"#text"` that is not a scalar, and on names
+/// that are not valid XML names. Error paths start with `name`.
+pub fn json_to_element(name: &str, v: &Value) -> Result<treexml::Element, UtilError> {
+    if !write::is_xml_name(name) {
+        return Err(UtilError::InvalidName { name: name.into() });
+    }
+    let mut e = treexml::Element::new(name);
+    fill(&mut e, v).map_err(|err| err.within(name))?;
+    Ok(e)
+}
+
+fn fill(e: &mut treexml::Element, v: &Value) -> Result<(), UtilError> {
+    let map = match v {
+        Value::Object(map) => map,
+        _ => {
+            e.text = text(v)?;
+            return Ok(());
+        }
+    };
+
+    for (k, v) in map {
+        match k.as_str() {
+            "@attrs" => {
+                let attrs = v.as_object().ok_or_else(|| invalid(v))?;
+                for (name, v) in attrs {
+                    if !write::is_xml_name(name) {
+                        return Err(UtilError::InvalidName { name: name.clone() });
+                    }
+                    let value = text(v)?.ok_or_else(|| invalid(v))?;
+                    e.attributes.insert(name.clone(), value);
+                }
+            }
+            "#text" => e.text = text(v)?,
+            _ => match v {
+                Value::Array(items) => {
+                    for (i, item) in items.iter().enumerate() {
+                        let child = json_to_element(k, item).map_err(|err| err.at_index(k, i))?;
+                        e.children.push(child);
+                    }
+                }
+                _ => e.children.push(json_to_element(k, v)?),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the text for a scalar, or `None` for `null`.
+fn text(v: &Value) -> Result<Option<String>, UtilError> {
+    match v {
+        Value::Null => Ok(None),
+        Value::Bool(b) => Ok(Some(b.to_string())),
+        Value::Number(n) => Ok(Some(n.to_string())),
+        Value::String(s) => Ok(Some(s.clone())),
+        Value::Array(_) | Value::Object(_) => Err(invalid(v)),
+    }
+}
+
+fn invalid(v: &Value) -> UtilError {
+    UtilError::InvalidValue {
+        path: String::new(),
+        value: v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_node, serialize_element};
+    use serde_json::json;
+
+    #[test]
+    fn test_element_to_json_repeated_children() {
+        let fixture = parse_node("<list><item>a</item><item>b</item><other/><item>c</item></list>")
+            .unwrap()
+            .unwrap();
+        let expectation = json!({"item": ["a", "b", "c"], "other": null});
+
+        let result = element_to_json(&fixture);
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_element_to_json_attrs_and_text() {
+        let fixture = parse_node(r#"<price currency="EUR" exact="true">12</price>"#)
+            .unwrap()
+            .unwrap();
+
+        let result = element_to_json(&fixture);
+        let expectation = json!({"@attrs": {"currency": "EUR", "exact": "true"}, "#text": "12"});
+        assert_eq!(expectation, result);
+
+        let opts = JsonOptions {
+            coerce_scalars: true,
+        };
+        let result = element_to_json_with(&fixture, &opts);
+        let expectation = json!({"@attrs": {"currency": "EUR", "exact": true}, "#text": 12});
+        assert_eq!(expectation, result);
+
+        let fixture = parse_node("<v>012</v>").unwrap().unwrap();
+        assert_eq!(json!("012"), element_to_json_with(&fixture, &opts));
+    }
+
+    #[test]
+    fn test_json_to_element_array() {
+        let fixture = json!({"@attrs": {"id": 7}, "item": ["a", {"#text": "b", "@attrs": {"x": "y"}}, null], "flag": true});
+        let expectation =
+            r#"<list id="7"><flag>true</flag><item>a</item><item x="y">b</item><item/></list>"#;
+
+        let opts = JsonOptions {
+            coerce_scalars: true,
+        };
+
+        let result = json_to_element("list", &fixture).unwrap();
+
+        assert_eq!(expectation, serialize_element(&result));
+        assert_eq!(fixture, element_to_json_with(&result, &opts));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let fixture = parse_node(
+            r#"<project id="1"><name>Boinc</name><task state="open">a</task><task>b</task><url/></project>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = json_to_element("project", &element_to_json(&fixture)).unwrap();
+
+        assert_eq!(serialize_element(&fixture), serialize_element(&result));
+    }
+
+    #[test]
+    fn test_json_to_element_errors() {
+        let e = json_to_element("list", &json!({"item": [["a"]]})).unwrap_err();
+        assert_eq!(r#"invalid value "[\"a\"]" at list.item[0]"#, e.to_string());
+
+        let e = json_to_element("e", &json!({"@attrs": {"a": {"b": 1}}})).unwrap_err();
+        assert_eq!(Some("e"), e.path());
+
+        let e = json_to_element("e", &json!({"not a name": 1})).unwrap_err();
+        assert!(matches!(e, UtilError::InvalidName { name } if name == "not a name"));
+    }
+}
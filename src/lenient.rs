@@ -0,0 +1,266 @@
+//! Repairs for common mistakes in real-world XML, applied before parsing.
+
+/// A repair made by `parse_node_lenient`. Offsets are byte positions in the original input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LenientFix {
+    /// A `&` that did not start a valid entity reference was escaped.
+    EscapedAmpersand { offset: usize },
+    /// A `<` that could not start markup was escaped.
+    EscapedLessThan { offset: usize },
+    /// A repeated attribute was dropped, keeping its first occurrence.
+    DroppedDuplicateAttribute {
+        offset: usize,
+        element: String,
+        name: String,
+    },
+}
+
+/// Returns `s` with the repairs applied, along with a record of each one.
+pub(crate) fn sanitize(s: &str) -> (String, Vec<LenientFix>) {
+    let mut sanitizer = Sanitizer {
+        s,
+        i: 0,
+        out: String::with_capacity(s.len()),
+        fixes: Vec::new(),
+    };
+    sanitizer.run();
+
+    (sanitizer.out, sanitizer.fixes)
+}
+
+struct Sanitizer<'a> {
+    s: &'a str,
+    i: usize,
+    out: String,
+    fixes: Vec<LenientFix>,
+}
+
+impl Sanitizer<'_> {
+    fn rest(&self) -> &str {
+        &self.s[self.i..]
+    }
+
+    fn run(&mut self) {
+        while self.i < self.s.len() {
+            let rest = self.rest();
+            if rest.starts_with('&') {
+                self.ampersand();
+            } else if rest.starts_with("<!--") {
+                self.copy_through("-->");
+            } else if rest.starts_with("<![CDATA[") {
+                self.copy_through("]]>");
+            } else if rest.starts_with("<?") {
+                self.copy_through("?>");
+            } else if rest.starts_with("<!") || rest.starts_with("</") {
+                self.copy_through(">");
+            } else if let Some(tail) = rest.strip_prefix('<') {
+                match tail.chars().next() {
+                    Some(c) if c.is_alphabetic() || c == '_' || c == ':' => self.start_tag(),
+                    _ => self.less_than(),
+                }
+            } else {
+                self.copy_char();
+            }
+        }
+    }
+
+    fn copy_char(&mut self) {
+        if let Some(c) = self.rest().chars().next() {
+            self.out.push(c);
+            self.i += c.len_utf8();
+        }
+    }
+
+    fn copy_through(&mut self, end: &str) {
+        let len = match self.rest().find(end) {
+            Some(n) => n + end.len(),
+            None => self.rest().len(),
+        };
+        self.out.push_str(&self.s[self.i..self.i + len]);
+        self.i += len;
+    }
+
+    fn ampersand(&mut self) {
+        if is_entity(self.rest()) {
+            self.out.push('&');
+        } else {
+            self.out.push_str("&amp;");
+            self.fixes
+                .push(LenientFix::EscapedAmpersand { offset: self.i });
+        }
+        self.i += 1;
+    }
+
+    fn less_than(&mut self) {
+        self.out.push_str("&lt;");
+        self.fixes
+            .push(LenientFix::EscapedLessThan { offset: self.i });
+        self.i += 1;
+    }
+
+    fn take_while<P>(&mut self, pred: P) -> &'_ str
+    where
+        P: Fn(char) -> bool,
+    {
+        let len = self
+            .rest()
+            .find(|c| !pred(c))
+            .unwrap_or_else(|| self.rest().len());
+        let start = self.i;
+        self.i += len;
+        &self.s[start..self.i]
+    }
+
+    fn start_tag(&mut self) {
+        self.i += 1;
+        let element = self
+            .take_while(|c| !c.is_whitespace() && c != '/' && c != '>')
+            .to_string();
+        self.out.push('<');
+        self.out.push_str(&element);
+
+        let mut seen = Vec::new();
+        loop {
+            let ws = self.take_while(char::is_whitespace).to_string();
+            self.out.push_str(&ws);
+
+            let rest = self.rest();
+            if rest.is_empty() {
+                return;
+            }
+            if rest.starts_with('>') || rest.starts_with("/>") {
+                self.copy_through(">");
+                return;
+            }
+
+            let offset = self.i;
+            let name = self
+                .take_while(|c| !c.is_whitespace() && c != '=' && c != '/' && c != '>')
+                .to_string();
+            if name.is_empty() {
+                self.copy_char();
+                continue;
+            }
+
+            let before_eq = self.take_while(char::is_whitespace).to_string();
+            if !self.rest().starts_with('=') {
+                self.out.push_str(&name);
+                self.out.push_str(&before_eq);
+                continue;
+            }
+            self.i += 1;
+            let after_eq = self.take_while(char::is_whitespace).to_string();
+            let prefix = format!("{}{}={}", name, before_eq, after_eq);
+
+            let quote = match self.rest().chars().next() {
+                Some(q @ '"') | Some(q @ '\'') => q,
+                _ => {
+                    self.out.push_str(&prefix);
+                    continue;
+                }
+            };
+            let value_start = self.i + 1;
+            let value_end = match self.rest()[1..].find(quote) {
+                Some(n) => value_start + n,
+                None => {
+                    self.out.push_str(&prefix);
+                    self.out.push_str(&self.s[self.i..]);
+                    self.i = self.s.len();
+                    return;
+                }
+            };
+            self.i = value_end + 1;
+
+            if seen.contains(&name) {
+                self.fixes.push(LenientFix::DroppedDuplicateAttribute {
+                    offset,
+                    element: element.clone(),
+                    name,
+                });
+                continue;
+            }
+
+            self.out.push_str(&prefix);
+            self.out.push(quote);
+            self.attribute_value(value_start, value_end);
+            self.out.push(quote);
+            seen.push(name);
+        }
+    }
+
+    fn attribute_value(&mut self, start: usize, end: usize) {
+        for (i, c) in self.s[start..end].char_indices() {
+            let offset = start + i;
+            match c {
+                '&' if !is_entity(&self.s[offset..end]) => {
+                    self.out.push_str("&amp;");
+                    self.fixes.push(LenientFix::EscapedAmpersand { offset });
+                }
+                '<' => {
+                    self.out.push_str("&lt;");
+                    self.fixes.push(LenientFix::EscapedLessThan { offset });
+                }
+                c => self.out.push(c),
+            }
+        }
+    }
+}
+
+/// Checks whether `s` starts with a predefined entity or a character reference.
+fn is_entity(s: &str) -> bool {
+    let body = match s.get(1..).and_then(|s| s.find(';').map(|n| &s[..n])) {
+        Some(body) => body,
+        None => return false,
+    };
+
+    if let Some(hex) = body.strip_prefix("#x") {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(dec) = body.strip_prefix('#') {
+        !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit())
+    } else {
+        ["amp", "lt", "gt", "quot", "apos"].contains(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_ampersand() {
+        let fixture = "<a href=\"x?a=1&b=2\">Tom &amp; Jerry & co &#38; &#x26;</a>";
+        let expectation = (
+            "<a href=\"x?a=1&amp;b=2\">Tom &amp; Jerry &amp; co &#38; &#x26;</a>".to_string(),
+            vec![
+                LenientFix::EscapedAmpersand { offset: 14 },
+                LenientFix::EscapedAmpersand { offset: 36 },
+            ],
+        );
+
+        let result = sanitize(fixture);
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_sanitize_markup() {
+        let fixture =
+            "<?xml version=\"1.0\"?><!-- a & b --><a x='1' y=\"2\" x=\"3\"><![CDATA[<&>]]>1 < 2</a>";
+        let expectation = (
+            "<?xml version=\"1.0\"?><!-- a & b --><a x='1' y=\"2\" ><![CDATA[<&>]]>1 &lt; 2</a>"
+                .to_string(),
+            vec![
+                LenientFix::DroppedDuplicateAttribute {
+                    offset: 50,
+                    element: "a".into(),
+                    name: "x".into(),
+                },
+                LenientFix::EscapedLessThan { offset: 73 },
+            ],
+        );
+
+        let result = sanitize(fixture);
+
+        assert_eq!(expectation, result);
+    }
+}
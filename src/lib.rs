@@ -1,231 +1,5681 @@
-use anyhow::format_err;
 use core::str::FromStr;
-use treexml::TreexmlError;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
 
-pub fn parse_node(s: &str) -> anyhow::Result<Option<treexml::Element>> {
-    let doc = treexml::Document::parse(s.as_bytes())?;
+mod canonical;
+mod compare;
+mod cursor;
+#[cfg(feature = "serde")]
+mod de;
+mod encoding;
+mod error;
+mod flatten;
+mod index;
+mod iter;
+#[cfg(feature = "json")]
+mod json;
+mod lenient;
+mod limits;
+mod location;
+mod merge;
+mod namespace;
+mod path;
+#[cfg(feature = "quick-xml")]
+mod quick;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod stream;
+#[cfg(feature = "test-util")]
+#[doc(hidden)]
+pub mod test_util;
+mod visit;
+mod write;
 
-    Ok(doc.root)
+pub use canonical::{canonical_string, canonicalize, CanonicalizeOptions};
+pub use compare::{deep_eq, diff_elements, DiffKind, ElementDiff, EqOptions};
+pub use cursor::Cursor;
+#[cfg(feature = "serde")]
+pub use de::from_element;
+pub use error::{HexError, UtilError};
+pub use flatten::{flatten, unflatten};
+pub use index::IndexedElement;
+pub use iter::Descendants;
+#[cfg(feature = "json")]
+pub use json::{element_to_json, element_to_json_with, json_to_element, JsonOptions};
+pub use lenient::LenientFix;
+pub use limits::{Limit, LimitExceeded, ParseLimits};
+pub use merge::{merge_elements, ChildMerge, MergePolicy};
+pub use path::Path;
+pub use schema::{validate, Kind, Occurs, Schema, ValidationError};
+#[cfg(feature = "serde")]
+pub use ser::to_element;
+pub use stream::extract_values;
+#[cfg(feature = "test-util")]
+pub use test_util::AsXml;
+pub use visit::{walk, VisitAction, Visitor};
+pub use write::{
+    serialize_compact, serialize_document, serialize_element, serialize_pretty, write_element,
+    Declaration, PrettyOptions, WriteOptions,
+};
+
+#[cfg(feature = "derive")]
+pub use treexml_util_derive::Unmarshaller;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use treexml::Element;
+}
+
+/// Parses a document, returning its root element.
+///
+/// Syntax errors carry the line and column of the failure. With the `quick-xml` feature, the tree
+/// is built with `quick-xml` instead of `treexml`, producing the same result faster.
+pub fn parse_node(s: &str) -> Result<Option<treexml::Element>, UtilError> {
+    #[cfg(feature = "quick-xml")]
+    {
+        if let Some(root) = quick::parse(s.as_bytes()) {
+            return Ok(Some(root));
+        }
+    }
+
+    parse_with_treexml(s.as_bytes())
+}
+
+/// Like `parse_node`, first repairing bare `&`, stray `<` and duplicate attributes.
+///
+/// Returns the repairs that were made alongside the root element. Input that is still not
+/// well-formed after the repairs is an error.
+pub fn parse_node_lenient(
+    s: &str,
+) -> Result<(Option<treexml::Element>, Vec<LenientFix>), UtilError> {
+    let (sanitized, fixes) = lenient::sanitize(s);
+
+    Ok((parse_node(&sanitized)?, fixes))
+}
+
+/// Like `parse_node`, rejecting documents that exceed `limits` before building the tree.
+///
+/// A tripped limit is reported as a `LimitExceeded` error.
+pub fn parse_node_with_limits(
+    s: &str,
+    limits: &ParseLimits,
+) -> Result<Option<treexml::Element>, UtilError> {
+    limits::check(s, limits)?;
+
+    parse_node(s)
+}
+
+/// Parses a snippet that may hold any number of top-level elements, returning them in order.
+///
+/// A leading XML declaration and whitespace between elements are skipped. Other text between
+/// top-level elements is an error.
+pub fn parse_fragment(s: &str) -> Result<Vec<treexml::Element>, UtilError> {
+    let mut s = s.trim_start_matches('\u{feff}').trim_start();
+    if s.starts_with("<?xml") {
+        let end = s.find("?>").ok_or_else(|| UtilError::Malformed {
+            reason: "unterminated XML declaration".into(),
+        })?;
+        s = &s[end + 2..];
+    }
+
+    let root = parse_node(&format!("<fragment>{}</fragment>", s))?.ok_or_else(|| {
+        UtilError::Malformed {
+            reason: "empty fragment".into(),
+        }
+    })?;
+    if root
+        .text
+        .iter()
+        .chain(&root.cdata)
+        .any(|t| !t.trim().is_empty())
+    {
+        return Err(UtilError::Malformed {
+            reason: "unexpected text between fragment elements".into(),
+        });
+    }
+
+    Ok(root.children)
+}
+
+/// Parses a document given as raw bytes in UTF-8, ISO-8859-1 or UTF-16.
+///
+/// The encoding is detected from the byte order mark or the XML declaration.
+pub fn parse_node_bytes(data: &[u8]) -> Result<Option<treexml::Element>, UtilError> {
+    parse_node(&encoding::decode_document(data)?)
+}
+
+/// Parses a document streamed from `r`, returning its root element.
+///
+/// Read failures are reported as parse errors. Syntax errors carry a position as in `parse_node`.
+/// The `quick-xml` feature applies here too; so that its errors are reported by `treexml`, the
+/// first 64 KiB are kept while it parses, and a larger document it rejects fails with `Malformed`.
+pub fn parse_node_from_reader<R>(r: R) -> Result<Option<treexml::Element>, UtilError>
+where
+    R: std::io::Read,
+{
+    #[cfg(feature = "quick-xml")]
+    let r = {
+        let mut r = location::Replay::new(r);
+        if let Some(root) = quick::parse(std::io::BufReader::new(&mut r)) {
+            return Ok(Some(root));
+        }
+        if !r.rewind() {
+            return Err(UtilError::Malformed {
+                reason: format!(
+                    "rejected by quick-xml past the first {} bytes, which are all that is kept",
+                    location::REPLAY_LIMIT
+                ),
+            });
+        }
+        r
+    };
+
+    parse_with_treexml(r)
+}
+
+fn parse_with_treexml<R>(r: R) -> Result<Option<treexml::Element>, UtilError>
+where
+    R: std::io::Read,
+{
+    treexml::Document::parse(r)
+        .map(|doc| doc.root)
+        .map_err(location::locate)
+}
+
+/// Like `parse_node`, keeping namespace declarations as `xmlns` and `xmlns:prefix` attributes.
+///
+/// `treexml` drops the declarations, so this is needed for namespace URIs to be resolved by
+/// `find_local` and its relatives.
+pub fn parse_node_with_namespaces(s: &str) -> Result<Option<treexml::Element>, UtilError> {
+    namespace::parse(s.as_bytes())
+}
+
+/// Like `parse_node`, then applies `strip_whitespace_text` to the result.
+pub fn parse_node_trimmed(s: &str) -> Result<Option<treexml::Element>, UtilError> {
+    let mut root = parse_node(s)?;
+    if let Some(e) = &mut root {
+        strip_whitespace_text(e);
+    }
+
+    Ok(root)
+}
+
+/// Trims the text and cdata of `e` and its descendants, turning whitespace-only values into `None`.
+///
+/// Useful for pretty-printed input, where indentation ends up in the text of elements.
+pub fn strip_whitespace_text(e: &mut treexml::Element) {
+    for value in [&mut e.text, &mut e.cdata].iter_mut() {
+        **value = value.take().and_then(|v| {
+            let trimmed = v.trim();
+            if trimmed.is_empty() {
+                None
+            } else if trimmed.len() == v.len() {
+                Some(v)
+            } else {
+                Some(trimmed.to_string())
+            }
+        });
+    }
+    for child in &mut e.children {
+        strip_whitespace_text(child);
+    }
+}
+
+/// Like `strip_whitespace_text`, leaving `e` untouched and returning a cleaned copy.
+pub fn strip_whitespace_text_cloned(e: &treexml::Element) -> treexml::Element {
+    let mut out = e.clone();
+    strip_whitespace_text(&mut out);
+    out
+}
+
+/// Parses the document stored at `p`, skipping a leading UTF-8 byte order mark.
+///
+/// Errors mention the file path.
+pub fn parse_node_from_file<P>(p: P) -> Result<Option<treexml::Element>, UtilError>
+where
+    P: AsRef<std::path::Path>,
+{
+    use std::io::BufRead;
+
+    let p = p.as_ref();
+    let io_error = |source| UtilError::Io {
+        path: p.display().to_string(),
+        source,
+    };
+    let f = std::fs::File::open(p).map_err(io_error)?;
+    let mut r = std::io::BufReader::new(f);
+
+    let has_bom = r.fill_buf().map_err(io_error)?.starts_with(b"\xEF\xBB\xBF");
+    if has_bom {
+        r.consume(3);
+    }
+
+    parse_node_from_reader(r).map_err(|e| UtilError::Parse {
+        path: p.display().to_string(),
+        source: Box::new(e),
+    })
+}
+
+pub fn trimmed_optional(e: &Option<String>) -> Option<String> {
+    e.clone().map(|v| v.trim().into())
+}
+
+/// Like `trimmed_optional`, treating a whitespace-only string as `None`.
+pub fn trimmed_nonempty(e: &Option<String>) -> Option<String> {
+    trimmed_str(e).map(String::from)
+}
+
+/// Like `trimmed_nonempty`, borrowing from `e` instead of cloning.
+pub fn trimmed_str(e: &Option<String>) -> Option<&str> {
+    e.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Lookup helpers for `treexml::Element`.
+///
+/// Paths are made of segments separated by `.` or `/`, each naming a child element (or `*` for any
+/// child) and optionally followed by a zero-based index, e.g. `result[1].name`, or an attribute
+/// predicate, e.g. `task[@name="foo"]`. When several elements match, the first one in document
+/// order is used. An empty path refers to the element itself. A `\` before a character makes it
+/// part of the name, so `app\.version` looks up an `<app.version>` child.
+pub trait ElementExt {
+    fn find_value0<T, PATH>(&self, path: PATH) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    fn find_value1<T, PATH>(&self, path: PATH) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    /// Like `find_value0`, failing with `AmbiguousPath` if more than one element matches `path`.
+    fn find_value_unique0<T, PATH>(&self, path: PATH) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    fn find_value_unique1<T, PATH>(&self, path: PATH) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    /// Finds the first element at a path parsed ahead of time.
+    fn find_path(&self, path: &Path) -> Option<&treexml::Element>;
+
+    /// Like `find_value0`, with a path parsed ahead of time.
+    fn find_value0_path<T>(&self, path: &Path) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    fn find_value1_path<T>(&self, path: &Path) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Finds an element matching path segments against local names, ignoring any `prefix:` part.
+    ///
+    /// With `ns` set, the element must also be in that namespace according to the `xmlns`
+    /// declarations on this element and its descendants, as kept by `parse_node_with_namespaces`.
+    fn find_local<PATH>(
+        &self,
+        path: PATH,
+        ns: Option<&str>,
+    ) -> Result<Option<&treexml::Element>, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Like `find_value0`, with the path resolved as in `find_local`.
+    fn find_value_local0<T, PATH>(
+        &self,
+        path: PATH,
+        ns: Option<&str>,
+    ) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    /// Like `find_value1`, with the path resolved as in `find_local`.
+    fn find_value_local1<T, PATH>(&self, path: PATH, ns: Option<&str>) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    /// Returns the CDATA content of the element at `path`.
+    fn find_cdata0<PATH>(&self, path: PATH) -> Result<Option<String>, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    fn find_cdata1<PATH>(&self, path: PATH) -> Result<String, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Finds a flag: an absent element is `false`, an empty one is `true`, otherwise the text, or
+    /// the CDATA content if there is no text, must be one of `true/false/1/0/yes/no/on/off`, compared case-insensitively and ignoring
+    /// surrounding whitespace.
+    fn find_bool<PATH>(&self, path: PATH) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Returns whether an element exists at `path`. An invalid path matches nothing.
+    fn has_child<PATH>(&self, path: PATH) -> bool
+    where
+        PATH: AsRef<str>;
+
+    /// Like `find_bool`, returning `None` for an absent element instead of `false`.
+    fn find_bool0<PATH>(&self, path: PATH) -> Result<Option<bool>, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Like `find_bool`, failing with `InvalidFlag` if the element has child elements or CDATA
+    /// content instead of treating it as `true`.
+    fn find_bool_strict<PATH>(&self, path: PATH) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Like `find_bool`, but with caller-supplied tokens. A token present in both lists is
+    /// rejected.
+    fn find_bool_with<PATH>(
+        &self,
+        path: PATH,
+        true_tokens: &[&str],
+        false_tokens: &[&str],
+    ) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    fn unmarshal_into<T>(&self, out: &mut T) -> Result<bool, UtilError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display;
+
+    /// Parses the text of this element, or its CDATA content if it has no text.
+    /// Returns `None` if it has neither.
+    fn unmarshal<T>(&self) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Like `unmarshal`, failing with `ValueNotFound` if there is nothing to parse.
+    fn unmarshal1<T>(&self) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Like `unmarshal_into`, ignoring whitespace around the text.
+    fn unmarshal_trimmed_into<T>(&self, out: &mut T) -> Result<bool, UtilError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display;
+
+    /// Reads this element as a flag, accepting the same tokens as `find_bool`. An element without
+    /// text is `true`.
+    fn unmarshal_bool_into(&self, out: &mut bool) -> Result<bool, UtilError>;
+
+    /// Unmarshals every child named `child_name` and appends it to `out`, returning the number of
+    /// items appended. `out` is left untouched if any child fails to unmarshal.
+    fn unmarshal_children_into<T>(
+        &self,
+        child_name: &str,
+        out: &mut Vec<T>,
+    ) -> Result<usize, UtilError>
+    where
+        T: Unmarshaller + Default;
+
+    /// Finds a timestamp given either as RFC 3339 or as (fractional) seconds since the Unix epoch.
+    #[cfg(feature = "chrono")]
+    fn find_datetime<PATH>(&self, path: PATH) -> Result<chrono::DateTime<chrono::Utc>, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    #[cfg(feature = "uuid")]
+    fn find_uuid<PATH>(&self, path: PATH) -> Result<uuid::Uuid, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Finds an absolute URL, ignoring whitespace around it.
+    #[cfg(feature = "url")]
+    fn find_url<PATH>(&self, path: PATH) -> Result<url::Url, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Dispatches every child to the target with the matching name, ignoring unknown children.
+    /// Returns the number of targets that were written to.
+    fn unmarshal_fields(
+        &self,
+        fields: &mut [(&str, &mut dyn Unmarshaller)],
+    ) -> Result<usize, UtilError>;
+
+    /// Maps the text at `path` to one of `variants` by exact token match, or ASCII case-insensitive
+    /// match if requested.
+    fn find_enum<T, PATH>(
+        &self,
+        path: PATH,
+        variants: &[(&str, T)],
+        case_insensitive: bool,
+    ) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: Clone;
+
+    /// Collects the values of every element matching `path`, skipping textless ones.
+    fn find_value_all<T, PATH>(&self, path: PATH) -> Result<Vec<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    /// Like `find_value_all`, with a path parsed ahead of time.
+    fn find_value_all_path<T>(&self, path: &Path) -> Result<Vec<T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Parses attribute `attr_name` of the element at `path`, or of `self` if `path` is empty.
+    fn find_attr_value0<T, PATH>(
+        &self,
+        path: PATH,
+        attr_name: &str,
+    ) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    fn find_attr_value1<T, PATH>(&self, path: PATH, attr_name: &str) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    /// Parses every attribute of this element as `T`. Fails with `InvalidAttribute` on the first
+    /// value that does not parse.
+    fn attrs_as<T>(&self) -> Result<HashMap<String, T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Returns the attributes of this element unparsed.
+    fn attrs_str(&self) -> &HashMap<String, String>;
+
+    /// Parses attribute `key` of this element, or else the content of its first child named `key`.
+    /// The attribute wins when both exist.
+    fn find_value_attr_or_child<T>(&self, key: &str) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Like `find_value_attr_or_child`, failing with `ValueNotFound` if neither exists.
+    fn find_value_attr_or_child1<T>(&self, key: &str) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Checks that an element exists at each of `paths`, reporting every missing one as
+    /// `ElementNotFound`.
+    fn validate_required(&self, paths: &[&str]) -> Result<(), Vec<UtilError>>;
+
+    /// Like `validate_required`, with `non_empty` also reporting elements without content as
+    /// `ValueNotFound`.
+    fn validate_required_with(&self, paths: &[&str], non_empty: bool)
+        -> Result<(), Vec<UtilError>>;
+
+    /// Checks that the content at each path parses as its `ValueKind`, reporting every failure.
+    /// Absent elements are not checked.
+    fn validate_types(&self, specs: &[(&str, ValueKind)]) -> Result<(), Vec<UtilError>>;
+
+    /// Returns the text followed by the CDATA content, or `None` if the element has neither.
+    fn text_content(&self) -> Option<String>;
+
+    /// Like `text_content`, trimmed the same way as `trimmed_nonempty`.
+    fn text_content_trimmed(&self) -> Option<String>;
+
+    /// Returns `default` if there is no value at `path`. A value that fails to parse is still an
+    /// error.
+    fn find_value_or<T, PATH>(&self, path: PATH, default: T) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr;
+
+    fn find_value_or_else<T, PATH, F>(&self, path: PATH, default: F) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+        F: FnOnce() -> T;
+
+    /// Returns the value at the first of `paths` that has one. Parse errors are not skipped over.
+    fn find_first_of<T>(&self, paths: &[&str]) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Like `find_first_of`, failing with `ValuesNotFound` if none of `paths` has a value.
+    fn find_first_of1<T>(&self, paths: &[&str]) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Finds the first element named `name` in the subtree, including `self`, searching in
+    /// pre-order.
+    fn find_descendant(&self, name: &str) -> Option<&treexml::Element>;
+
+    fn find_descendant_value0<T>(&self, name: &str) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr;
+
+    /// Collects every element named `name` in the subtree, including `self`, in pre-order.
+    fn find_descendants(&self, name: &str) -> Vec<&treexml::Element>;
+
+    /// Iterates over the direct children named `name` without cloning them.
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a treexml::Element>;
+
+    fn children_named_mut<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a mut treexml::Element>;
+
+    /// Counts the direct children named `name`.
+    fn count_children(&self, name: &str) -> usize;
+
+    /// Counts the elements matching `path`. An invalid path matches nothing.
+    fn count_matching(&self, path: &str) -> usize;
+
+    /// Returns whether the element has no text, CDATA, attributes or children.
+    fn is_empty_element(&self) -> bool;
+
+    /// Iterates over `self` and all of its descendants in pre-order.
+    fn descendants(&self) -> impl Iterator<Item = &treexml::Element>;
+
+    /// Like `descendants`, also yielding the depth relative to `self`.
+    fn descendants_with_depth(&self) -> Descendants<'_>;
+
+    /// Returns the first direct child satisfying `pred`.
+    fn find_child_by<P>(&self, pred: P) -> Option<&treexml::Element>
+    where
+        P: Fn(&treexml::Element) -> bool;
+
+    fn find_child_by_mut<P>(&mut self, pred: P) -> Option<&mut treexml::Element>
+    where
+        P: Fn(&treexml::Element) -> bool;
+
+    /// Iterates over the direct children satisfying `pred`.
+    fn filter_children_by<P>(&self, pred: P) -> impl Iterator<Item = &treexml::Element>
+    where
+        P: Fn(&treexml::Element) -> bool;
+
+    /// Finds the element at `path` for modification.
+    fn find_mut<PATH>(&mut self, path: PATH) -> Result<&mut treexml::Element, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    fn find_mut0<PATH>(&mut self, path: PATH) -> Result<Option<&mut treexml::Element>, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Replaces the content of the element at `path` with text, removing any CDATA.
+    fn set_text<PATH, T>(&mut self, path: PATH, value: T) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::fmt::Display;
+
+    /// Replaces the content of the element at `path` with CDATA, removing any text.
+    fn set_cdata<PATH, T>(&mut self, path: PATH, value: T) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::fmt::Display;
+
+    /// Removes both text and CDATA from the element at `path`.
+    fn clear_content<PATH>(&mut self, path: PATH) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Sets attribute `name` on the element at `path`, overwriting any previous value.
+    fn set_attr<PATH, V>(&mut self, path: PATH, name: &str, value: V) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+        V: std::fmt::Display;
+
+    /// Removes attribute `name` from the element at `path`, returning whether it was present.
+    fn remove_attr<PATH>(&mut self, path: PATH, name: &str) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Appends `child` to the element at `path`.
+    fn insert_child<PATH>(&mut self, path: PATH, child: treexml::Element) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Inserts `child` at position `index` among the children of the element at `path`.
+    fn insert_child_at<PATH>(
+        &mut self,
+        path: PATH,
+        index: usize,
+        child: treexml::Element,
+    ) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Replaces the child at position `index` of the element at `path`, returning the old child.
+    fn replace_child<PATH>(
+        &mut self,
+        path: PATH,
+        index: usize,
+        child: treexml::Element,
+    ) -> Result<treexml::Element, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Removes every child named `name` from the element at `path`, returning how many were
+    /// removed.
+    fn remove_children<PATH>(&mut self, path: PATH, name: &str) -> Result<usize, UtilError>
+    where
+        PATH: AsRef<str>;
+
+    /// Detaches the first direct child named `name` and returns it by value.
+    fn take_child(&mut self, name: &str) -> Option<treexml::Element>;
+
+    /// Detaches every direct child named `name`, preserving their order.
+    fn take_children(&mut self, name: &str) -> Vec<treexml::Element>;
+
+    /// Sorts the direct children with `cmp`, keeping equal children in order.
+    fn sort_children_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&treexml::Element, &treexml::Element) -> Ordering;
+
+    /// Like `sort_children_by`, applied to every element of the subtree.
+    fn sort_children_by_recursive<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&treexml::Element, &treexml::Element) -> Ordering;
+
+    /// Sorts the direct children by the key extracted with `f`, keeping equal children in order.
+    fn sort_children_by_key<K, F>(&mut self, f: F)
+    where
+        K: Ord,
+        F: FnMut(&treexml::Element) -> K;
+
+    /// Like `sort_children_by_key`, applied to every element of the subtree.
+    fn sort_children_by_key_recursive<K, F>(&mut self, f: F)
+    where
+        K: Ord,
+        F: FnMut(&treexml::Element) -> K;
+
+    /// Keeps only the direct children satisfying `pred`, returning how many were removed.
+    fn retain_children<P>(&mut self, pred: P) -> usize
+    where
+        P: FnMut(&treexml::Element) -> bool;
+
+    /// Like `retain_children`, applied to every element of the subtree.
+    ///
+    /// Children of removed elements are not visited.
+    fn retain_children_recursive<P>(&mut self, pred: P) -> usize
+    where
+        P: FnMut(&treexml::Element) -> bool;
+
+    /// Rewrites the text and cdata of `self` and its descendants.
+    ///
+    /// `f` receives the element name and the current value and returns a replacement, or `None`
+    /// to leave the value unchanged.
+    fn map_text<F>(&mut self, f: F)
+    where
+        F: FnMut(&str, &str) -> Option<String>;
 }
 
-pub fn trimmed_optional(e: &Option<String>) -> Option<String> {
-    e.clone().map(|v| v.trim().into())
-}
+impl ElementExt for treexml::Element {
+    fn find_value0<T, PATH>(&self, path: PATH) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        parse_content(find_element(self, path)?, path)
+    }
+
+    fn find_value1<T, PATH>(&self, path: PATH) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        self.find_value0(path)
+            .and_then(|v| v.ok_or_else(|| UtilError::ValueNotFound { path: path.into() }))
+    }
+
+    fn find_value_unique0<T, PATH>(&self, path: PATH) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        let matches = path::resolve(self, &path::parse(path)?);
+        if matches.len() > 1 {
+            return Err(UtilError::AmbiguousPath {
+                path: path.into(),
+                count: matches.len(),
+            });
+        }
+
+        parse_content(matches.first().copied(), path)
+    }
+
+    fn find_value_unique1<T, PATH>(&self, path: PATH) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        self.find_value_unique0(path)?
+            .ok_or_else(|| UtilError::ValueNotFound { path: path.into() })
+    }
+
+    fn find_path(&self, path: &Path) -> Option<&treexml::Element> {
+        path::resolve_first(self, path.segments())
+    }
+
+    fn find_value0_path<T>(&self, path: &Path) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        parse_content(self.find_path(path), path.as_str())
+    }
+
+    fn find_value1_path<T>(&self, path: &Path) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        self.find_value0_path(path)?
+            .ok_or_else(|| UtilError::ValueNotFound {
+                path: path.to_string(),
+            })
+    }
+
+    fn find_local<PATH>(
+        &self,
+        path: PATH,
+        ns: Option<&str>,
+    ) -> Result<Option<&treexml::Element>, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        Ok(namespace::resolve_local(self, &path::parse(path)?, ns)
+            .into_iter()
+            .next())
+    }
+
+    fn find_value_local0<T, PATH>(
+        &self,
+        path: PATH,
+        ns: Option<&str>,
+    ) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        parse_content(self.find_local(path, ns)?, path)
+    }
+
+    fn find_value_local1<T, PATH>(&self, path: PATH, ns: Option<&str>) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        self.find_value_local0(path, ns)
+            .and_then(|v| v.ok_or_else(|| UtilError::ValueNotFound { path: path.into() }))
+    }
+
+    fn find_bool<PATH>(&self, path: PATH) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        self.find_bool_with(path, TRUE_TOKENS, FALSE_TOKENS)
+    }
+
+    fn has_child<PATH>(&self, path: PATH) -> bool
+    where
+        PATH: AsRef<str>,
+    {
+        matches!(find_element(self, path.as_ref()), Ok(Some(_)))
+    }
+
+    fn find_bool0<PATH>(&self, path: PATH) -> Result<Option<bool>, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        bool_token(
+            find_element(self, path)?,
+            path,
+            TRUE_TOKENS,
+            FALSE_TOKENS,
+            false,
+        )
+    }
+
+    fn find_bool_strict<PATH>(&self, path: PATH) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        bool_token(
+            find_element(self, path)?,
+            path,
+            TRUE_TOKENS,
+            FALSE_TOKENS,
+            true,
+        )
+        .map(|v| v.unwrap_or(false))
+    }
+
+    fn find_bool_with<PATH>(
+        &self,
+        path: PATH,
+        true_tokens: &[&str],
+        false_tokens: &[&str],
+    ) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        if let Some(token) = true_tokens
+            .iter()
+            .find(|t| false_tokens.iter().any(|f| t.eq_ignore_ascii_case(f)))
+        {
+            return Err(UtilError::AmbiguousBoolToken {
+                token: token.to_string(),
+            });
+        }
+
+        let path = path.as_ref();
+        bool_token(
+            find_element(self, path)?,
+            path,
+            true_tokens,
+            false_tokens,
+            false,
+        )
+        .map(|v| v.unwrap_or(false))
+    }
+
+    fn unmarshal_into<T>(&self, out: &mut T) -> Result<bool, UtilError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match content(self) {
+            None => Ok(false),
+            Some(text) => {
+                *out = match T::from_str(text) {
+                    Ok(v) => v,
+                    Err(_) => return Err(invalid_value(self, text)),
+                };
+                Ok(true)
+            }
+        }
+    }
+
+    fn unmarshal<T>(&self) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        parse_content(Some(self), &self.name)
+    }
+
+    fn unmarshal1<T>(&self) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        self.unmarshal()?.ok_or_else(|| UtilError::ValueNotFound {
+            path: self.name.clone(),
+        })
+    }
+
+    fn unmarshal_trimmed_into<T>(&self, out: &mut T) -> Result<bool, UtilError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match content(self) {
+            None => Ok(false),
+            Some(text) => {
+                *out = match T::from_str(text.trim()) {
+                    Ok(v) => v,
+                    Err(_) => return Err(invalid_value(self, text)),
+                };
+                Ok(true)
+            }
+        }
+    }
+
+    fn unmarshal_bool_into(&self, out: &mut bool) -> Result<bool, UtilError> {
+        match content(self) {
+            None => {
+                *out = true;
+                Ok(true)
+            }
+            Some(text) => {
+                *out = parse_bool_token(text, TRUE_TOKENS, FALSE_TOKENS)
+                    .ok_or_else(|| invalid_value(self, text))?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn unmarshal_children_into<T>(
+        &self,
+        child_name: &str,
+        out: &mut Vec<T>,
+    ) -> Result<usize, UtilError>
+    where
+        T: Unmarshaller + Default,
+    {
+        let mut items = Vec::new();
+        for (i, child) in self.children_named(child_name).enumerate() {
+            let mut v = T::default();
+            v.unmarshal_from(child)
+                .map_err(|e| e.at_index(child_name, i))?;
+            items.push(v);
+        }
+
+        let n = items.len();
+        out.append(&mut items);
+        Ok(n)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn find_datetime<PATH>(&self, path: PATH) -> Result<chrono::DateTime<chrono::Utc>, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        let text = self.find_value1::<String, _>(path)?;
+        parse_datetime(&text).ok_or_else(|| UtilError::InvalidValue {
+            path: path.into(),
+            value: text.clone(),
+        })
+    }
+
+    #[cfg(feature = "uuid")]
+    fn find_uuid<PATH>(&self, path: PATH) -> Result<uuid::Uuid, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        self.find_value1(path)
+    }
+
+    #[cfg(feature = "url")]
+    fn find_url<PATH>(&self, path: PATH) -> Result<url::Url, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        let text = self.find_value1::<String, _>(path)?;
+        parse_url(&text, path)
+    }
+
+    fn unmarshal_fields(
+        &self,
+        fields: &mut [(&str, &mut dyn Unmarshaller)],
+    ) -> Result<usize, UtilError> {
+        let mut filled = vec![false; fields.len()];
+        for child in &self.children {
+            for (i, (name, target)) in fields.iter_mut().enumerate() {
+                if *name != child.name {
+                    continue;
+                }
+
+                if target.unmarshal_from(child).map_err(|e| UtilError::Field {
+                    field: child.name.clone(),
+                    source: Box::new(e),
+                })? {
+                    filled[i] = true;
+                }
+            }
+        }
+
+        Ok(filled.into_iter().filter(|v| *v).count())
+    }
+
+    fn find_enum<T, PATH>(
+        &self,
+        path: PATH,
+        variants: &[(&str, T)],
+        case_insensitive: bool,
+    ) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: Clone,
+    {
+        let path = path.as_ref();
+        let text = self.find_value1::<String, _>(path)?;
+
+        variants
+            .iter()
+            .find(|(token, _)| {
+                if case_insensitive {
+                    token.eq_ignore_ascii_case(&text)
+                } else {
+                    *token == text
+                }
+            })
+            .map(|(_, v)| v.clone())
+            .ok_or_else(|| UtilError::UnknownVariant {
+                path: path.into(),
+                value: text.clone(),
+                expected: variants
+                    .iter()
+                    .map(|(token, _)| *token)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+
+    fn find_value_all<T, PATH>(&self, path: PATH) -> Result<Vec<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        parse_all(path::resolve(self, &path::parse(path)?), path)
+    }
+
+    fn find_value_all_path<T>(&self, path: &Path) -> Result<Vec<T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        parse_all(path::resolve(self, path.segments()), path.as_str())
+    }
+
+    fn find_attr_value0<T, PATH>(&self, path: PATH, attr_name: &str) -> Result<Option<T>, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        let e = match find_element(self, path)? {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        match e.attributes.get(attr_name) {
+            None => Ok(None),
+            Some(v) => match T::from_str(v) {
+                Ok(v) => Ok(Some(v)),
+                Err(_) => Err(UtilError::InvalidAttribute {
+                    path: path.into(),
+                    name: attr_name.into(),
+                    value: v.clone(),
+                }),
+            },
+        }
+    }
+
+    fn find_attr_value1<T, PATH>(&self, path: PATH, attr_name: &str) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        let path = path.as_ref();
+        self.find_attr_value0(path, attr_name)?
+            .ok_or_else(|| UtilError::AttributeNotFound {
+                path: path.into(),
+                name: attr_name.into(),
+            })
+    }
+
+    fn attrs_as<T>(&self) -> Result<HashMap<String, T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        self.attributes
+            .iter()
+            .map(|(k, v)| match T::from_str(v) {
+                Ok(parsed) => Ok((k.clone(), parsed)),
+                Err(_) => Err(UtilError::InvalidAttribute {
+                    path: String::new(),
+                    name: k.clone(),
+                    value: v.clone(),
+                }),
+            })
+            .collect()
+    }
+
+    fn attrs_str(&self) -> &HashMap<String, String> {
+        &self.attributes
+    }
+
+    fn find_value_attr_or_child<T>(&self, key: &str) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        match self.attributes.get(key) {
+            Some(v) => match T::from_str(v) {
+                Ok(v) => Ok(Some(v)),
+                Err(_) => Err(UtilError::InvalidAttribute {
+                    path: String::new(),
+                    name: key.into(),
+                    value: v.clone(),
+                }),
+            },
+            None => parse_content(self.children_named(key).next(), key),
+        }
+    }
+
+    fn find_value_attr_or_child1<T>(&self, key: &str) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        self.find_value_attr_or_child(key)?
+            .ok_or_else(|| UtilError::ValueNotFound { path: key.into() })
+    }
+
+    fn validate_required(&self, paths: &[&str]) -> Result<(), Vec<UtilError>> {
+        self.validate_required_with(paths, false)
+    }
+
+    fn validate_required_with(
+        &self,
+        paths: &[&str],
+        non_empty: bool,
+    ) -> Result<(), Vec<UtilError>> {
+        collect_failures(paths.iter().map(|&path| match find_element(self, path)? {
+            None => Err(UtilError::ElementNotFound { path: path.into() }),
+            Some(e) if non_empty && e.text_content_trimmed().is_none() => {
+                Err(UtilError::ValueNotFound { path: path.into() })
+            }
+            Some(_) => Ok(()),
+        }))
+    }
+
+    fn validate_types(&self, specs: &[(&str, ValueKind)]) -> Result<(), Vec<UtilError>> {
+        collect_failures(
+            specs
+                .iter()
+                .map(|&(path, kind)| match find_element(self, path)? {
+                    Some(e) => check_value(e, path, kind),
+                    None => Ok(()),
+                }),
+        )
+    }
+
+    fn find_cdata0<PATH>(&self, path: PATH) -> Result<Option<String>, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        Ok(find_element(self, path)?.and_then(|e| e.cdata.clone()))
+    }
+
+    fn find_cdata1<PATH>(&self, path: PATH) -> Result<String, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        self.find_cdata0(path)?
+            .ok_or_else(|| UtilError::CdataNotFound { path: path.into() })
+    }
+
+    fn text_content(&self) -> Option<String> {
+        match (&self.text, &self.cdata) {
+            (None, None) => None,
+            (text, cdata) => Some(format!(
+                "{}{}",
+                text.as_deref().unwrap_or_default(),
+                cdata.as_deref().unwrap_or_default()
+            )),
+        }
+    }
+
+    fn text_content_trimmed(&self) -> Option<String> {
+        trimmed_nonempty(&self.text_content())
+    }
+
+    fn find_value_or<T, PATH>(&self, path: PATH, default: T) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+    {
+        Ok(self.find_value0(path)?.unwrap_or(default))
+    }
+
+    fn find_value_or_else<T, PATH, F>(&self, path: PATH, default: F) -> Result<T, UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::str::FromStr,
+        F: FnOnce() -> T,
+    {
+        Ok(self.find_value0(path)?.unwrap_or_else(default))
+    }
+
+    fn find_first_of<T>(&self, paths: &[&str]) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        for path in paths {
+            if let Some(v) = self.find_value0(*path)? {
+                return Ok(Some(v));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn find_first_of1<T>(&self, paths: &[&str]) -> Result<T, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        self.find_first_of(paths)?
+            .ok_or_else(|| UtilError::ValuesNotFound {
+                paths: paths.iter().map(|p| p.to_string()).collect(),
+            })
+    }
+
+    fn find_descendant(&self, name: &str) -> Option<&treexml::Element> {
+        if self.name == name {
+            return Some(self);
+        }
+
+        self.children.iter().find_map(|c| c.find_descendant(name))
+    }
+
+    fn find_descendant_value0<T>(&self, name: &str) -> Result<Option<T>, UtilError>
+    where
+        T: std::str::FromStr,
+    {
+        match self.find_descendant(name).and_then(content) {
+            None => Ok(None),
+            Some(text) => match T::from_str(text) {
+                Ok(v) => Ok(Some(v)),
+                Err(_) => Err(UtilError::InvalidValue {
+                    path: name.into(),
+                    value: text.clone(),
+                }),
+            },
+        }
+    }
+
+    fn find_descendants(&self, name: &str) -> Vec<&treexml::Element> {
+        fn collect<'a>(e: &'a treexml::Element, name: &str, out: &mut Vec<&'a treexml::Element>) {
+            if e.name == name {
+                out.push(e);
+            }
+            for c in &e.children {
+                collect(c, name, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        collect(self, name, &mut out);
+        out
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a treexml::Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    fn children_named_mut<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a mut treexml::Element> {
+        self.children.iter_mut().filter(move |c| c.name == name)
+    }
+
+    fn count_children(&self, name: &str) -> usize {
+        self.children_named(name).count()
+    }
+
+    fn count_matching(&self, path: &str) -> usize {
+        path::parse(path).map_or(0, |segments| path::resolve(self, &segments).len())
+    }
+
+    fn is_empty_element(&self) -> bool {
+        self.text.is_none()
+            && self.cdata.is_none()
+            && self.attributes.is_empty()
+            && self.children.is_empty()
+    }
+
+    fn descendants(&self) -> impl Iterator<Item = &treexml::Element> {
+        Descendants::new(self).map(|(_, e)| e)
+    }
+
+    fn descendants_with_depth(&self) -> Descendants<'_> {
+        Descendants::new(self)
+    }
+
+    fn find_child_by<P>(&self, pred: P) -> Option<&treexml::Element>
+    where
+        P: Fn(&treexml::Element) -> bool,
+    {
+        self.children.iter().find(|c| pred(c))
+    }
+
+    fn find_child_by_mut<P>(&mut self, pred: P) -> Option<&mut treexml::Element>
+    where
+        P: Fn(&treexml::Element) -> bool,
+    {
+        self.children.iter_mut().find(|c| pred(c))
+    }
+
+    fn filter_children_by<P>(&self, pred: P) -> impl Iterator<Item = &treexml::Element>
+    where
+        P: Fn(&treexml::Element) -> bool,
+    {
+        self.children.iter().filter(move |c| pred(c))
+    }
+
+    fn find_mut<PATH>(&mut self, path: PATH) -> Result<&mut treexml::Element, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        self.find_mut0(path)?
+            .ok_or_else(|| UtilError::ElementNotFound { path: path.into() })
+    }
+
+    fn find_mut0<PATH>(&mut self, path: PATH) -> Result<Option<&mut treexml::Element>, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        Ok(path::resolve_first_mut(self, &path::parse(path)?))
+    }
+
+    fn set_text<PATH, T>(&mut self, path: PATH, value: T) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::fmt::Display,
+    {
+        let e = self.find_mut(path)?;
+        e.text = Some(value.to_string());
+        e.cdata = None;
+        Ok(())
+    }
+
+    fn set_cdata<PATH, T>(&mut self, path: PATH, value: T) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+        T: std::fmt::Display,
+    {
+        let e = self.find_mut(path)?;
+        e.text = None;
+        e.cdata = Some(value.to_string());
+        Ok(())
+    }
+
+    fn clear_content<PATH>(&mut self, path: PATH) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let e = self.find_mut(path)?;
+        e.text = None;
+        e.cdata = None;
+        Ok(())
+    }
+
+    fn set_attr<PATH, V>(&mut self, path: PATH, name: &str, value: V) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+        V: std::fmt::Display,
+    {
+        self.find_mut(path)?
+            .attributes
+            .insert(name.into(), value.to_string());
+        Ok(())
+    }
+
+    fn remove_attr<PATH>(&mut self, path: PATH, name: &str) -> Result<bool, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        Ok(self.find_mut(path)?.attributes.remove(name).is_some())
+    }
+
+    fn insert_child<PATH>(&mut self, path: PATH, child: treexml::Element) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        self.find_mut(path)?.children.push(child);
+        Ok(())
+    }
+
+    fn insert_child_at<PATH>(
+        &mut self,
+        path: PATH,
+        index: usize,
+        child: treexml::Element,
+    ) -> Result<(), UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        let e = self.find_mut(path)?;
+        if index > e.children.len() {
+            return Err(UtilError::IndexOutOfRange {
+                path: path.into(),
+                index,
+                len: e.children.len(),
+            });
+        }
+
+        e.children.insert(index, child);
+        Ok(())
+    }
+
+    fn replace_child<PATH>(
+        &mut self,
+        path: PATH,
+        index: usize,
+        child: treexml::Element,
+    ) -> Result<treexml::Element, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let path = path.as_ref();
+        let e = self.find_mut(path)?;
+        let n = e.children.len();
+        match e.children.get_mut(index) {
+            Some(old) => Ok(std::mem::replace(old, child)),
+            None => Err(UtilError::IndexOutOfRange {
+                path: path.into(),
+                index,
+                len: n,
+            }),
+        }
+    }
+
+    fn remove_children<PATH>(&mut self, path: PATH, name: &str) -> Result<usize, UtilError>
+    where
+        PATH: AsRef<str>,
+    {
+        let e = self.find_mut(path)?;
+        let n = e.children.len();
+        e.children.retain(|c| c.name != name);
+        Ok(n - e.children.len())
+    }
+
+    fn take_child(&mut self, name: &str) -> Option<treexml::Element> {
+        let i = self.children.iter().position(|c| c.name == name)?;
+        Some(self.children.remove(i))
+    }
+
+    fn take_children(&mut self, name: &str) -> Vec<treexml::Element> {
+        let (taken, kept) = std::mem::take(&mut self.children)
+            .into_iter()
+            .partition(|c| c.name == name);
+        self.children = kept;
+        taken
+    }
+
+    fn sort_children_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&treexml::Element, &treexml::Element) -> Ordering,
+    {
+        self.children.sort_by(|a, b| cmp(a, b));
+    }
+
+    fn sort_children_by_recursive<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&treexml::Element, &treexml::Element) -> Ordering,
+    {
+        sort_recursive(self, &mut cmp);
+    }
+
+    fn sort_children_by_key<K, F>(&mut self, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&treexml::Element) -> K,
+    {
+        self.children.sort_by_key(|c| f(c));
+    }
+
+    fn sort_children_by_key_recursive<K, F>(&mut self, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&treexml::Element) -> K,
+    {
+        sort_recursive(self, &mut |a: &treexml::Element, b: &treexml::Element| {
+            f(a).cmp(&f(b))
+        });
+    }
+
+    fn retain_children<P>(&mut self, mut pred: P) -> usize
+    where
+        P: FnMut(&treexml::Element) -> bool,
+    {
+        let before = self.children.len();
+        self.children.retain(|c| pred(c));
+        before - self.children.len()
+    }
+
+    fn retain_children_recursive<P>(&mut self, mut pred: P) -> usize
+    where
+        P: FnMut(&treexml::Element) -> bool,
+    {
+        retain_recursive(self, &mut pred)
+    }
+
+    fn map_text<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &str) -> Option<String>,
+    {
+        map_text_recursive(self, &mut f);
+    }
+}
+
+/// The type `validate_types` checks content against. Numbers may have whitespace around them, and
+/// booleans use the `find_bool` rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    /// Parses as `i64`.
+    Int,
+    /// Parses as `f64`.
+    Float,
+    Bool,
+    /// Any content, or none.
+    String,
+}
+
+/// Checks that the content of `e`, found at `path`, parses as `kind`.
+fn check_value(e: &treexml::Element, path: &str, kind: ValueKind) -> Result<(), UtilError> {
+    let parses = |text: &str| match kind {
+        ValueKind::Int => i64::from_str(text.trim()).is_ok(),
+        ValueKind::Float => f64::from_str(text.trim()).is_ok(),
+        ValueKind::Bool | ValueKind::String => true,
+    };
+
+    match kind {
+        ValueKind::Bool => bool_token(Some(e), path, TRUE_TOKENS, FALSE_TOKENS, false).map(|_| ()),
+        ValueKind::String => Ok(()),
+        ValueKind::Int | ValueKind::Float => match content(e) {
+            None => Err(UtilError::ValueNotFound { path: path.into() }),
+            Some(text) if parses(text) => Ok(()),
+            Some(text) => Err(UtilError::InvalidValue {
+                path: path.into(),
+                value: text.clone(),
+            }),
+        },
+    }
+}
+
+/// Gathers the errors of `checks`, or `Ok` if there are none.
+fn collect_failures<I>(checks: I) -> Result<(), Vec<UtilError>>
+where
+    I: Iterator<Item = Result<(), UtilError>>,
+{
+    let errors = checks.filter_map(Result::err).collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+const TRUE_TOKENS: &[&str] = &["true", "1", "yes", "on"];
+const FALSE_TOKENS: &[&str] = &["false", "0", "no", "off"];
+
+fn sort_recursive<F>(e: &mut treexml::Element, cmp: &mut F)
+where
+    F: FnMut(&treexml::Element, &treexml::Element) -> Ordering,
+{
+    e.children.sort_by(|a, b| cmp(a, b));
+    for child in &mut e.children {
+        sort_recursive(child, cmp);
+    }
+}
+
+fn retain_recursive<P>(e: &mut treexml::Element, pred: &mut P) -> usize
+where
+    P: FnMut(&treexml::Element) -> bool,
+{
+    let before = e.children.len();
+    e.children.retain(|c| pred(c));
+    let removed = before - e.children.len();
+
+    removed
+        + e.children
+            .iter_mut()
+            .map(|c| retain_recursive(c, pred))
+            .sum::<usize>()
+}
+
+fn map_text_recursive<F>(e: &mut treexml::Element, f: &mut F)
+where
+    F: FnMut(&str, &str) -> Option<String>,
+{
+    for value in e.text.iter_mut().chain(e.cdata.iter_mut()) {
+        if let Some(v) = f(&e.name, value) {
+            *value = v;
+        }
+    }
+    for child in &mut e.children {
+        map_text_recursive(child, f);
+    }
+}
+
+/// Matches `text`, trimmed, against the tokens case-insensitively.
+fn parse_bool_token(text: &str, true_tokens: &[&str], false_tokens: &[&str]) -> Option<bool> {
+    let text = text.trim();
+    if true_tokens.iter().any(|t| t.eq_ignore_ascii_case(text)) {
+        Some(true)
+    } else if false_tokens.iter().any(|t| t.eq_ignore_ascii_case(text)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Reads `e`, found at `path`, as a flag, or `None` if there is no element. The token falls back
+/// to CDATA content like values do, except with `strict`, where a flag with children or CDATA is
+/// an error.
+fn bool_token(
+    e: Option<&treexml::Element>,
+    path: &str,
+    true_tokens: &[&str],
+    false_tokens: &[&str],
+    strict: bool,
+) -> Result<Option<bool>, UtilError> {
+    let e = match e {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+    if strict && (!e.children.is_empty() || e.cdata.is_some()) {
+        return Err(UtilError::InvalidFlag { path: path.into() });
+    }
+    match content(e) {
+        None => Ok(Some(true)),
+        Some(text) => parse_bool_token(text, true_tokens, false_tokens)
+            .map(Some)
+            .ok_or_else(|| UtilError::InvalidBool {
+                path: path.into(),
+                token: text.clone(),
+            }),
+    }
+}
+
+fn invalid_value(node: &treexml::Element, text: &str) -> UtilError {
+    UtilError::InvalidValue {
+        path: node.name.clone(),
+        value: text.into(),
+    }
+}
+
+/// Parses the content of `e`, found at `path`, if it has any.
+fn parse_content<T>(e: Option<&treexml::Element>, path: &str) -> Result<Option<T>, UtilError>
+where
+    T: FromStr,
+{
+    match e.and_then(content) {
+        None => Ok(None),
+        Some(text) => match T::from_str(text) {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => Err(UtilError::InvalidValue {
+                path: path.into(),
+                value: text.clone(),
+            }),
+        },
+    }
+}
+
+/// Parses the content of every element of `elements`, all found at `path`, skipping textless ones.
+/// A failure is reported at `path[i]`, `i` being the position of the element among the matches.
+fn parse_all<T>(elements: Vec<&treexml::Element>, path: &str) -> Result<Vec<T>, UtilError>
+where
+    T: FromStr,
+{
+    let mut out = Vec::new();
+    for (i, e) in elements.into_iter().enumerate() {
+        if let Some(text) = content(e) {
+            out.push(T::from_str(text).map_err(|_| UtilError::InvalidValue {
+                path: format!("{}[{}]", path, i),
+                value: text.clone(),
+            })?);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Returns the text of `e`, falling back to its CDATA content.
+fn content(e: &treexml::Element) -> Option<&String> {
+    e.text.as_ref().or(e.cdata.as_ref())
+}
+
+/// Resolves `path` relative to `e` using the syntax described in the `path` module.
+fn find_element<'a>(
+    e: &'a treexml::Element,
+    path: &str,
+) -> Result<Option<&'a treexml::Element>, UtilError> {
+    Ok(path::resolve_first(e, &path::parse(path)?))
+}
+
+/// Parses `text`, found at `path`, as an absolute URL.
+#[cfg(feature = "url")]
+fn parse_url(text: &str, path: &str) -> Result<url::Url, UtilError> {
+    url::Url::parse(text.trim()).map_err(|reason| UtilError::InvalidUrl {
+        path: path.into(),
+        value: text.into(),
+        reason,
+    })
+}
+
+#[cfg(feature = "chrono")]
+fn parse_datetime(text: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let text = text.trim();
+    if let Ok(v) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Some(v.with_timezone(&chrono::Utc));
+    }
+
+    let secs = f64::from_str(text).ok().filter(|v| v.is_finite())?;
+    let whole = secs.floor();
+    chrono::DateTime::from_timestamp(whole as i64, ((secs - whole) * 1e9) as u32)
+}
+
+pub trait Unmarshaller {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError>;
+}
+
+/// Accepts any node, for fields where only presence matters.
+impl Unmarshaller for () {
+    fn unmarshal_from(&mut self, _node: &treexml::Element) -> Result<bool, UtilError> {
+        Ok(true)
+    }
+}
+
+impl Unmarshaller for bool {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        node.unmarshal_bool_into(self)
+    }
+}
+
+macro_rules! impl_unmarshaller_from_str {
+    ($($t:ty),*) => {
+        $(
+            impl Unmarshaller for $t {
+                fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+                    node.unmarshal_into(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_unmarshaller_from_str!(String);
+
+macro_rules! impl_unmarshaller_numeric {
+    ($($t:ty),*) => {
+        $(
+            /// Whitespace around the number is ignored, since pretty-printed documents put it
+            /// there.
+            impl Unmarshaller for $t {
+                fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+                    node.unmarshal_trimmed_into(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_unmarshaller_numeric!(i8, i16, i32, i64, i128, isize);
+impl_unmarshaller_numeric!(u8, u16, u32, u64, u128, usize);
+impl_unmarshaller_numeric!(f32, f64);
+
+impl<T> Unmarshaller for Option<T>
+where
+    T: Unmarshaller + Default,
+{
+    /// Stores `Some(value)` if the inner type accepted the node. The original value is left
+    /// untouched otherwise, including on error.
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let mut v = T::default();
+        if !v.unmarshal_from(node)? {
+            return Ok(false);
+        }
+
+        *self = Some(v);
+        Ok(true)
+    }
+}
+
+macro_rules! impl_unmarshaller_address {
+    ($($t:ty),*) => {
+        $(
+            /// Unlike most impls, an element without text is an error since there is no sensible
+            /// default address.
+            impl Unmarshaller for $t {
+                fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+                    if content(node).is_none() {
+                        return Err(invalid_value(node, ""));
+                    }
+                    node.unmarshal_into(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_unmarshaller_address!(
+    std::net::IpAddr,
+    std::net::Ipv4Addr,
+    std::net::Ipv6Addr,
+    std::net::SocketAddr
+);
+
+macro_rules! impl_unmarshaller_non_zero {
+    ($($t:ty => $inner:ty),*) => {
+        $(
+            /// Zero is rejected as `ZeroValue` holding the text as written, e.g. `00`.
+            impl Unmarshaller for $t {
+                fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+                    let text = match content(node) {
+                        Some(text) => text,
+                        None => return Ok(false),
+                    };
+                    let v = <$inner>::from_str(text.trim()).map_err(|_| invalid_value(node, text))?;
+                    if v == 0 {
+                        return Err(UtilError::ZeroValue {
+                            path: node.name.clone(),
+                            value: text.clone(),
+                        });
+                    }
+
+                    *self = <$t>::new(v).expect("checked for zero");
+                    Ok(true)
+                }
+            }
+        )*
+    };
+}
+
+impl_unmarshaller_non_zero!(
+    std::num::NonZeroI8 => i8,
+    std::num::NonZeroI16 => i16,
+    std::num::NonZeroI32 => i32,
+    std::num::NonZeroI64 => i64,
+    std::num::NonZeroIsize => isize,
+    std::num::NonZeroU8 => u8,
+    std::num::NonZeroU16 => u16,
+    std::num::NonZeroU32 => u32,
+    std::num::NonZeroU64 => u64,
+    std::num::NonZeroUsize => usize
+);
+
+impl Unmarshaller for char {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                let mut chars = text.chars();
+                *self = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(invalid_value(node, text)),
+                };
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Takes the text verbatim. Surrounding whitespace is preserved since it is legal in paths.
+impl Unmarshaller for std::path::PathBuf {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                *self = text.into();
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Parses the text as a number of seconds, which may be fractional.
+impl Unmarshaller for std::time::Duration {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let text = match content(node) {
+            Some(text) => text,
+            None => return Ok(false),
+        };
+
+        *self = match text
+            .trim()
+            .parse()
+            .map(std::time::Duration::try_from_secs_f64)
+        {
+            Ok(Ok(v)) => v,
+            _ => return Err(invalid_value(node, text)),
+        };
+        Ok(true)
+    }
+}
+
+/// Accepts RFC 3339 as well as (fractional) seconds since the Unix epoch.
+#[cfg(feature = "chrono")]
+impl Unmarshaller for chrono::DateTime<chrono::Utc> {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                *self = parse_datetime(text).ok_or_else(|| invalid_value(node, text))?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Accepts the same formats as the `DateTime<Utc>` impl, yielding the UTC wall clock time.
+#[cfg(feature = "chrono")]
+impl Unmarshaller for chrono::NaiveDateTime {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let mut v = chrono::DateTime::<chrono::Utc>::default();
+        if !v.unmarshal_from(node)? {
+            return Ok(false);
+        }
+
+        *self = v.naive_utc();
+        Ok(true)
+    }
+}
+
+/// Accepts the hyphenated, simple, braced and URN forms.
+#[cfg(feature = "uuid")]
+impl Unmarshaller for uuid::Uuid {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                *self = match uuid::Uuid::parse_str(text.trim()) {
+                    Ok(v) => v,
+                    Err(_) => return Err(invalid_value(node, text)),
+                };
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Accepts absolute URLs only, ignoring whitespace around them.
+#[cfg(feature = "url")]
+impl Unmarshaller for url::Url {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                *self = parse_url(text, &node.name)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Parses whitespace-separated values, requiring exactly `N` of them.
+impl<T, const N: usize> Unmarshaller for [T; N]
+where
+    T: FromStr + Default + Copy,
+    T::Err: std::fmt::Display,
+{
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let text = match content(node) {
+            None => return Ok(false),
+            Some(text) => text,
+        };
+
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        if tokens.len() != N {
+            return Err(UtilError::WrongCount {
+                element: node.name.clone(),
+                expected: N,
+                found: tokens.len(),
+            });
+        }
+
+        let mut values = [T::default(); N];
+        for (v, token) in values.iter_mut().zip(tokens) {
+            *v = match T::from_str(token) {
+                Ok(v) => v,
+                Err(_) => return Err(invalid_value(node, token)),
+            };
+        }
+
+        *self = values;
+        Ok(true)
+    }
+}
+
+/// Adapter giving any `FromStr` type an `Unmarshaller` impl that parses the element text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FromText<T>(pub T);
+
+impl<T> FromText<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for FromText<T> {
+    fn from(v: T) -> Self {
+        Self(v)
+    }
+}
+
+impl<T> std::ops::Deref for FromText<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for FromText<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// Falls back to CDATA like `unmarshal_into`. Whitespace around the text is kept.
+impl<T> Unmarshaller for FromText<T>
+where
+    T: FromStr + Default,
+    T::Err: std::fmt::Display,
+{
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        node.unmarshal_into(&mut self.0)
+    }
+}
+
+/// Binary payload encoded as hexadecimal text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HexBytes(pub Vec<u8>);
+
+/// Accepts digits of either case with an optional `0x` prefix.
+impl Unmarshaller for HexBytes {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                self.0 = decode_hex(text).map_err(|source| UtilError::InvalidHex {
+                    path: node.name.clone(),
+                    value: text.clone(),
+                    source,
+                })?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>, HexError> {
+    let (offset, digits) = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(digits) => (2, digits),
+        None => (0, text),
+    };
+    if digits.len() % 2 != 0 {
+        return Err(HexError::OddLength { len: digits.len() });
+    }
+
+    let mut out = Vec::with_capacity(digits.len() / 2);
+    let mut byte = 0u8;
+    for (i, c) in digits.chars().enumerate() {
+        let nibble = c.to_digit(16).ok_or(HexError::InvalidCharacter {
+            character: c,
+            position: offset + i,
+        })?;
+        byte = (byte << 4) | nibble as u8;
+        if i % 2 == 1 {
+            out.push(byte);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Binary payload encoded as standard base64 text.
+#[cfg(feature = "base64")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Base64Bytes(pub Vec<u8>);
+
+/// Whitespace inside the encoded text is ignored, so wrapped lines are accepted.
+#[cfg(feature = "base64")]
+impl Unmarshaller for Base64Bytes {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        use base64::Engine;
+
+        match content(node) {
+            None => Ok(false),
+            Some(text) => {
+                let encoded = text
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>();
+                self.0 = match base64::engine::general_purpose::STANDARD.decode(encoded) {
+                    Ok(v) => v,
+                    Err(_) => return Err(invalid_value(node, text)),
+                };
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Collects child elements as `name => text` pairs, using an empty string for textless children.
+/// Duplicate child names and children with nested elements are rejected, leaving the map untouched.
+impl Unmarshaller for HashMap<String, String> {
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let mut entries = HashMap::new();
+        for child in &node.children {
+            if !child.children.is_empty() {
+                return Err(UtilError::NestedMapEntry {
+                    element: format!("{}.{}", node.name, child.name),
+                });
+            }
+            let v = content(child).cloned().unwrap_or_default();
+            if entries.insert(child.name.clone(), v).is_some() {
+                return Err(UtilError::DuplicateKey {
+                    element: node.name.clone(),
+                    key: child.name.clone(),
+                });
+            }
+        }
+
+        let written = !entries.is_empty();
+        self.extend(entries);
+        Ok(written)
+    }
+}
+
+/// Collects every child element. Duplicate values are silently merged.
+impl<T> Unmarshaller for HashSet<T>
+where
+    T: Unmarshaller + Default + Eq + Hash,
+{
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let items = unmarshal_children::<T>(node)?;
+
+        let written = !items.is_empty();
+        self.extend(items);
+        Ok(written)
+    }
+}
+
+/// Collects every child element in sorted order. Duplicate values are silently merged.
+impl<T> Unmarshaller for BTreeSet<T>
+where
+    T: Unmarshaller + Default + Ord,
+{
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let items = unmarshal_children::<T>(node)?;
+
+        let written = !items.is_empty();
+        self.extend(items);
+        Ok(written)
+    }
+}
+
+/// Collects child elements keyed by element name. Duplicate child names are rejected, leaving the
+/// map untouched.
+impl<T> Unmarshaller for BTreeMap<String, T>
+where
+    T: Unmarshaller + Default,
+{
+    fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+        let mut entries = BTreeMap::new();
+        for child in &node.children {
+            let mut v = T::default();
+            v.unmarshal_from(child).map_err(|e| e.within(&node.name))?;
+            if entries.insert(child.name.clone(), v).is_some() {
+                return Err(UtilError::DuplicateKey {
+                    element: node.name.clone(),
+                    key: child.name.clone(),
+                });
+            }
+        }
+
+        let written = !entries.is_empty();
+        self.append(&mut entries);
+        Ok(written)
+    }
+}
+
+macro_rules! impl_unmarshaller_tuple {
+    ($n:expr => $($t:ident . $i:tt),+) => {
+        /// Unmarshals children positionally, requiring exactly one child per component.
+        impl<$($t),+> Unmarshaller for ($($t,)+)
+        where
+            $($t: Unmarshaller + Default),+
+        {
+            fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+                if node.children.len() != $n {
+                    return Err(UtilError::WrongCount {
+                        element: node.name.clone(),
+                        expected: $n,
+                        found: node.children.len(),
+                    });
+                }
+
+                let mut v = ($($t::default(),)+);
+                $(unmarshal_child(&mut v.$i, node, $i)?;)+
+
+                *self = v;
+                Ok(true)
+            }
+        }
+    };
+}
+
+impl_unmarshaller_tuple!(1 => A.0);
+impl_unmarshaller_tuple!(2 => A.0, B.1);
+impl_unmarshaller_tuple!(3 => A.0, B.1, C.2);
+impl_unmarshaller_tuple!(4 => A.0, B.1, C.2, D.3);
+
+fn unmarshal_children<T>(node: &treexml::Element) -> Result<Vec<T>, UtilError>
+where
+    T: Unmarshaller + Default,
+{
+    (0..node.children.len())
+        .map(|i| {
+            let mut v = T::default();
+            unmarshal_child(&mut v, node, i)?;
+            Ok(v)
+        })
+        .collect()
+}
+
+/// Unmarshals the `i`-th child of `node` into `v`, reporting errors at `node.child`, or at
+/// `node.child[n]` if the child has siblings with the same name.
+fn unmarshal_child<T>(v: &mut T, node: &treexml::Element, i: usize) -> Result<bool, UtilError>
+where
+    T: Unmarshaller,
+{
+    let child = &node.children[i];
+    v.unmarshal_from(child).map_err(|e| {
+        let same_name = |c: &&treexml::Element| c.name == child.name;
+        let e = if node.children.iter().filter(same_name).count() > 1 {
+            let n = node.children[..i].iter().filter(same_name).count();
+            e.at_index(&child.name, n)
+        } else {
+            e
+        };
+        e.within(&node.name)
+    })
+}
+
+/// Like `Unmarshaller`, reading a value from attribute `name` of `e` instead of its content.
+/// Returns `Ok(false)` and leaves `self` untouched if the attribute is absent.
+pub trait UnmarshalAttr {
+    fn unmarshal_attr(&mut self, e: &treexml::Element, name: &str) -> Result<bool, UtilError>;
+}
+
+impl UnmarshalAttr for bool {
+    fn unmarshal_attr(&mut self, e: &treexml::Element, name: &str) -> Result<bool, UtilError> {
+        unmarshal_attr_with(self, e, name, |v| {
+            parse_bool_token(v, TRUE_TOKENS, FALSE_TOKENS)
+        })
+    }
+}
+
+impl UnmarshalAttr for String {
+    fn unmarshal_attr(&mut self, e: &treexml::Element, name: &str) -> Result<bool, UtilError> {
+        unmarshal_attr_with(self, e, name, |v| Some(v.to_string()))
+    }
+}
+
+macro_rules! impl_unmarshal_attr_numeric {
+    ($($t:ty),*) => {
+        $(
+            impl UnmarshalAttr for $t {
+                fn unmarshal_attr(&mut self, e: &treexml::Element, name: &str) -> Result<bool, UtilError> {
+                    unmarshal_attr_with(self, e, name, |v| <$t>::from_str(v.trim()).ok())
+                }
+            }
+        )*
+    };
+}
+
+impl_unmarshal_attr_numeric!(i8, i16, i32, i64, i128, isize);
+impl_unmarshal_attr_numeric!(u8, u16, u32, u64, u128, usize);
+impl_unmarshal_attr_numeric!(f32, f64);
+
+fn unmarshal_attr_with<T, F>(
+    out: &mut T,
+    e: &treexml::Element,
+    name: &str,
+    parse: F,
+) -> Result<bool, UtilError>
+where
+    F: FnOnce(&str) -> Option<T>,
+{
+    let value = match e.attributes.get(name) {
+        Some(v) => v,
+        None => return Ok(false),
+    };
+    *out = parse(value).ok_or_else(|| UtilError::InvalidAttribute {
+        path: String::new(),
+        name: name.into(),
+        value: value.clone(),
+    })?;
+    Ok(true)
+}
+
+pub trait Marshaller {
+    fn marshal_into(&self, name: &str) -> treexml::Element;
+}
+
+impl Marshaller for bool {
+    fn marshal_into(&self, name: &str) -> treexml::Element {
+        make_text_element(name, if *self { "true" } else { "false" })
+    }
+}
+
+impl Marshaller for i64 {
+    fn marshal_into(&self, name: &str) -> treexml::Element {
+        make_text_element(name, self)
+    }
+}
+
+impl Marshaller for f64 {
+    fn marshal_into(&self, name: &str) -> treexml::Element {
+        make_text_element(name, self)
+    }
+}
+
+impl Marshaller for String {
+    fn marshal_into(&self, name: &str) -> treexml::Element {
+        make_text_element(name, self)
+    }
+}
+
+/// Creates an XML element that contains child elements
+pub fn make_tree_element(name: &str, v: Vec<treexml::Element>) -> treexml::Element {
+    treexml::Element {
+        name: name.into(),
+        children: v,
+        ..Default::default()
+    }
+}
+
+/// Creates an XML element with text contents
+pub fn make_text_element<T>(name: &str, v: T) -> treexml::Element
+where
+    T: std::fmt::Display,
+{
+    treexml::Element {
+        name: name.into(),
+        text: Some(v.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Creates an XML element with cdata contents
+pub fn make_cdata_element<T>(name: &str, v: T) -> treexml::Element
+where
+    T: std::fmt::Display,
+{
+    treexml::Element {
+        name: name.into(),
+        cdata: Some(v.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Creates an XML element that contains child elements and has the given attributes
+///
+/// Attributes are stored in a map, so their order is not kept; serializers write them sorted by
+/// key. Later entries for the same key replace earlier ones.
+pub fn make_tree_element_attrs(
+    name: &str,
+    attrs: &[(&str, &dyn std::fmt::Display)],
+    v: Vec<treexml::Element>,
+) -> treexml::Element {
+    treexml::Element {
+        attributes: make_attrs(attrs),
+        ..make_tree_element(name, v)
+    }
+}
+
+/// Creates an XML element with text contents and the given attributes
+pub fn make_text_element_attrs<T>(
+    name: &str,
+    attrs: &[(&str, &dyn std::fmt::Display)],
+    v: T,
+) -> treexml::Element
+where
+    T: std::fmt::Display,
+{
+    treexml::Element {
+        attributes: make_attrs(attrs),
+        ..make_text_element(name, v)
+    }
+}
+
+/// Creates an XML element with cdata contents and the given attributes
+pub fn make_cdata_element_attrs<T>(
+    name: &str,
+    attrs: &[(&str, &dyn std::fmt::Display)],
+    v: T,
+) -> treexml::Element
+where
+    T: std::fmt::Display,
+{
+    treexml::Element {
+        attributes: make_attrs(attrs),
+        ..make_cdata_element(name, v)
+    }
+}
+
+/// Creates an empty XML element defined only by its attributes, as in `<file_ref file_name="x"/>`
+///
+/// Values are formatted with `Display`, like the contents of `make_text_element`.
+pub fn make_attr_element<I, K, V>(name: &str, attrs: I) -> treexml::Element
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: std::fmt::Display,
+{
+    treexml::Element {
+        name: name.into(),
+        attributes: attrs
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.to_string()))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn make_attrs(attrs: &[(&str, &dyn std::fmt::Display)]) -> HashMap<String, String> {
+    attrs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Like `make_cdata_element`, rejecting content that cannot be stored in a single CDATA section.
+///
+/// Fails if the content contains `]]>` or characters that XML 1.0 does not allow.
+pub fn try_make_cdata_element<T>(name: &str, v: T) -> Result<treexml::Element, UtilError>
+where
+    T: std::fmt::Display,
+{
+    let v = v.to_string();
+    if v.contains("]]>") {
+        return Err(UtilError::InvalidCdata {
+            element: name.into(),
+            reason: "contains ]]>".into(),
+        });
+    }
+    if let Some(c) = v.chars().find(|c| !write::is_xml_char(*c)) {
+        return Err(UtilError::InvalidCdata {
+            element: name.into(),
+            reason: format!("contains character {:?} not allowed in XML", c),
+        });
+    }
+
+    Ok(make_cdata_element(name, v))
+}
+
+/// Creates an XML element with one text child named `item` for each of `values`
+pub fn make_list_element<T, I>(parent: &str, item: &str, values: I) -> treexml::Element
+where
+    T: std::fmt::Display,
+    I: IntoIterator<Item = T>,
+{
+    make_tree_element(parent, make_element_list(item, values))
+}
+
+/// Creates a text element named `item` for each of `values`, for splicing into an existing parent
+pub fn make_element_list<T, I>(item: &str, values: I) -> Vec<treexml::Element>
+where
+    T: std::fmt::Display,
+    I: IntoIterator<Item = T>,
+{
+    values
+        .into_iter()
+        .map(|v| make_text_element(item, v))
+        .collect()
+}
+
+/// Creates an XML element with a text child for each entry, named by its key
+///
+/// This is the inverse of the `HashMap` unmarshaller. Fails on keys that are not valid XML names.
+pub fn make_map_element<K, V>(
+    name: &str,
+    entries: impl IntoIterator<Item = (K, V)>,
+) -> Result<treexml::Element, UtilError>
+where
+    K: AsRef<str>,
+    V: std::fmt::Display,
+{
+    let children = entries
+        .into_iter()
+        .map(|(k, v)| {
+            let k = k.as_ref();
+            if !write::is_xml_name(k) {
+                return Err(UtilError::InvalidName { name: k.into() });
+            }
+            Ok(make_text_element(k, v))
+        })
+        .collect::<Result<_, UtilError>>()?;
+
+    Ok(make_tree_element(name, children))
+}
+
+/// Creates an XML element with text contents if `v` is set
+pub fn make_optional_text_element<T>(name: &str, v: &Option<T>) -> Option<treexml::Element>
+where
+    T: std::fmt::Display,
+{
+    v.as_ref().map(|v| make_text_element(name, v))
+}
+
+/// Creates an empty flag element such as `<suspended/>` if `v` is true, the form `find_bool` reads
+/// back as `true`, and `None` otherwise.
+pub fn make_optional_bool_element(name: &str, v: bool) -> Option<treexml::Element> {
+    make_bool_element(name, v, BoolStyle::PresenceOnly)
+}
+
+/// How `make_bool_element` represents a boolean
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoolStyle {
+    /// `<x>1</x>` and `<x>0</x>`
+    Numeric,
+    /// `<x>true</x>` and `<x>false</x>`
+    Word,
+    /// `<x/>` for true, no element for false
+    PresenceOnly,
+}
+
+/// Creates an XML element holding `v` in the given style, or `None` for a false `PresenceOnly` flag
+///
+/// Every style is read back by `find_bool`.
+pub fn make_bool_element(name: &str, v: bool, style: BoolStyle) -> Option<treexml::Element> {
+    match style {
+        BoolStyle::Numeric => Some(make_text_element(name, u8::from(v))),
+        BoolStyle::Word => Some(make_text_element(name, v)),
+        BoolStyle::PresenceOnly if v => Some(make_tree_element(name, vec![])),
+        BoolStyle::PresenceOnly => None,
+    }
+}
+
+/// Appends a text element to `children` if `v` is set
+pub fn push_optional<T>(children: &mut Vec<treexml::Element>, name: &str, v: &Option<T>)
+where
+    T: std::fmt::Display,
+{
+    children.extend(make_optional_text_element(name, v));
+}
+
+/// Creates an XML element named `prefix:local` that contains child elements
+///
+/// With `ns_uri` set, the element declares its namespace through an `xmlns` or `xmlns:prefix`
+/// attribute. Descendants reusing a prefix declared on an ancestor should pass `None` instead.
+pub fn make_ns_element(
+    prefix: Option<&str>,
+    local: &str,
+    ns_uri: Option<&str>,
+    children: Vec<treexml::Element>,
+) -> treexml::Element {
+    let mut e = treexml::Element {
+        prefix: prefix.map(Into::into),
+        ..make_tree_element(local, children)
+    };
+    if let Some(uri) = ns_uri {
+        declare_namespace(&mut e, prefix, uri);
+    }
+
+    e
+}
+
+/// Declares the namespace `uri` on `e`, bound to `prefix` or as the default namespace
+///
+/// Declaring every namespace once on the root lets descendants use the prefixes without repeating
+/// them.
+pub fn declare_namespace(e: &mut treexml::Element, prefix: Option<&str>, uri: &str) {
+    let key = match prefix {
+        Some(prefix) => format!("xmlns:{}", prefix),
+        None => "xmlns".to_string(),
+    };
+    e.attributes.insert(key, uri.into());
+}
+
+/// Creates an XML element with lowercase hex-encoded contents
+pub fn make_hex_element(name: &str, v: &[u8]) -> treexml::Element {
+    make_text_element(
+        name,
+        v.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+    )
+}
+
+/// Creates an XML element with base64-encoded contents
+#[cfg(feature = "base64")]
+pub fn make_base64_element(name: &str, v: &[u8]) -> treexml::Element {
+    use base64::Engine;
+
+    make_text_element(name, base64::engine::general_purpose::STANDARD.encode(v))
+}
+
+/// Builds an XML element tree from a literal description, expanding to the `make_*` constructors
+///
+/// Children are separated by commas: `"name": value` is a text element, `"name" { ... }` a nested
+/// element and `..children` splices in any `IntoIterator` of elements. Attributes go in brackets
+/// after a name, as in `"file_ref" ["file_name" = name] {}`. Values are formatted with `Display`.
+///
+/// ```
+/// # use treexml_util::element;
+/// let name_var = "uppercase";
+/// let e = element!("get_results" { "active_only": 1, "match" { "name": name_var } });
+/// ```
+#[macro_export]
+macro_rules! element {
+    ($name:literal $([$($attrs:tt)*])? { $($body:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut e = $crate::make_tree_element_attrs(
+            $name,
+            $crate::element!(@attrs $($($attrs)*)?),
+            ::std::vec::Vec::new(),
+        );
+        $crate::element!(@children e; $($body)*);
+        e
+    }};
+    ($name:literal $([$($attrs:tt)*])?) => {
+        $crate::element!($name $([$($attrs)*])? {})
+    };
+    (@attrs $($k:literal = $v:expr),* $(,)?) => {
+        &[$(($k, &$v as &dyn ::std::fmt::Display)),*]
+    };
+    (@children $e:ident;) => {};
+    (@children $e:ident; .. $list:expr $(, $($rest:tt)*)?) => {
+        $e.children.extend($list);
+        $crate::element!(@children $e; $($($rest)*)?);
+    };
+    (@children $e:ident; $k:literal $([$($attrs:tt)*])? : $v:expr $(, $($rest:tt)*)?) => {
+        $e.children.push($crate::make_text_element_attrs(
+            $k,
+            $crate::element!(@attrs $($($attrs)*)?),
+            $v,
+        ));
+        $crate::element!(@children $e; $($($rest)*)?);
+    };
+    (@children $e:ident; $k:literal $([$($attrs:tt)*])? { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $e.children.push($crate::element!($k $([$($attrs)*])? { $($inner)* }));
+        $crate::element!(@children $e; $($($rest)*)?);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_deserialize() {
+        let fixture = parse_node("<data>5</data>").unwrap().unwrap();
+        let expectation = 5;
+
+        let mut result = i64::default();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_deserialize_bool() {
+        let fixture = parse_node("<do_want/>").unwrap().unwrap();
+        let expectation = true;
+
+        let mut result = bool::default();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_find_value() {
+        let root = treexml::Element {
+            name: "root".into(),
+            children: vec![treexml::Element {
+                name: "key".into(),
+                text: Some("value".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let expectation = "value".to_string();
+
+        let result = root.find_value1::<String, _>("key").unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    fn unmarshal_text<T>(text: &str) -> Result<T, UtilError>
+    where
+        T: Unmarshaller + Default,
+    {
+        unmarshal_text_with(text, T::default())
+    }
+
+    fn unmarshal_text_with<T>(text: &str, mut result: T) -> Result<T, UtilError>
+    where
+        T: Unmarshaller,
+    {
+        let fixture = make_text_element("data", text);
+
+        result.unmarshal_from(&fixture)?;
+
+        Ok(result)
+    }
+
+    fn unmarshal_cdata<T>(cdata: &str) -> Result<T, UtilError>
+    where
+        T: Unmarshaller + Default,
+    {
+        unmarshal_cdata_with(cdata, T::default())
+    }
+
+    fn unmarshal_cdata_with<T>(cdata: &str, mut result: T) -> Result<T, UtilError>
+    where
+        T: Unmarshaller,
+    {
+        let fixture = make_cdata_element("data", cdata);
+
+        assert!(result.unmarshal_from(&fixture)?, "{:?}", cdata);
+
+        Ok(result)
+    }
+
+    fn is_value_from_str(e: &UtilError) -> bool {
+        matches!(e, UtilError::InvalidValue { .. })
+    }
+
+    #[test]
+    fn test_deserialize_integer_bounds() {
+        assert_eq!(i8::MIN, unmarshal_text::<i8>("-128").unwrap());
+        assert_eq!(i16::MIN, unmarshal_text::<i16>("-32768").unwrap());
+        assert_eq!(i32::MIN, unmarshal_text::<i32>("-2147483648").unwrap());
+        assert_eq!(u8::MAX, unmarshal_text::<u8>("255").unwrap());
+        assert_eq!(u16::MAX, unmarshal_text::<u16>("65535").unwrap());
+        assert_eq!(u32::MAX, unmarshal_text::<u32>("4294967295").unwrap());
+        assert_eq!(
+            u64::MAX,
+            unmarshal_text::<u64>("18446744073709551615").unwrap()
+        );
+        assert_eq!(
+            usize::MAX,
+            unmarshal_text::<usize>(&usize::MAX.to_string()).unwrap()
+        );
+        assert_eq!(
+            isize::MIN,
+            unmarshal_text::<isize>(&isize::MIN.to_string()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unmarshal() {
+        let fixture =
+            parse_node("<host><ncpus>4</ncpus><ram/><name>x</name><id><![CDATA[7]]></id></host>")
+                .unwrap()
+                .unwrap();
+        let child = |name| fixture.find_child(|c| c.name == name).unwrap();
+
+        assert_eq!(Some(4), child("ncpus").unmarshal::<i64>().unwrap());
+        assert_eq!(4, child("ncpus").unmarshal1::<i64>().unwrap());
+        assert_eq!(7, child("id").unmarshal1::<i64>().unwrap());
+
+        assert_eq!(None, child("ram").unmarshal::<i64>().unwrap());
+        assert!(matches!(
+            child("ram").unmarshal1::<i64>(),
+            Err(UtilError::ValueNotFound { path }) if path == "ram"
+        ));
+
+        assert!(matches!(
+            child("name").unmarshal::<i64>(),
+            Err(UtilError::InvalidValue { path, value }) if path == "name" && value == "x"
+        ));
+        assert!(is_value_from_str(
+            &child("name").unmarshal1::<i64>().unwrap_err()
+        ));
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct HostId(String);
+
+    impl FromStr for HostId {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.strip_prefix("host-") {
+                Some(id) if !id.is_empty() => Ok(HostId(id.into())),
+                _ => Err(format!("not a host id: {}", s)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_text() {
+        let mut result = FromText(HostId::default());
+        assert!(result
+            .unmarshal_from(&make_text_element("id", "host-42"))
+            .unwrap());
+        assert_eq!("42", result.0 .0);
+        assert_eq!(HostId("42".into()), result.into_inner());
+
+        let mut result = FromText::from(HostId::default());
+        let e = result
+            .unmarshal_from(&make_text_element("id", "42"))
+            .unwrap_err();
+        assert!(is_value_from_str(&e));
+
+        let mut result = FromText::<HostId>::default();
+        assert!(!result
+            .unmarshal_from(&make_tree_element("id", vec![]))
+            .unwrap());
+        assert_eq!(HostId::default(), *result);
+    }
+
+    #[test]
+    fn test_from_text_parse_int_error() {
+        let mut result = FromText(7u16);
+
+        let e = result
+            .unmarshal_from(&make_text_element("port", "70000"))
+            .unwrap_err();
+
+        assert!(
+            matches!(e, UtilError::InvalidValue { path, value } if path == "port" && value == "70000")
+        );
+        assert_eq!(7, *result);
+
+        *result += 1;
+        result
+            .unmarshal_from(&make_text_element("port", "8080"))
+            .unwrap();
+        assert_eq!(8080, *result);
+    }
+
+    #[test]
+    fn test_deserialize_numeric_pretty_printed() {
+        let fixture = parse_node("<stats>\n  <count>\n    42\n  </count>\n  <credit> 1.5\t</credit>\n  <bad>4 2</bad>\n</stats>")
+            .unwrap()
+            .unwrap();
+
+        let mut count = 0i64;
+        assert!(count
+            .unmarshal_from(fixture.find("count").unwrap())
+            .unwrap());
+        assert_eq!(42, count);
+
+        let mut credit = 0f64;
+        assert!(credit
+            .unmarshal_from(fixture.find("credit").unwrap())
+            .unwrap());
+        assert_eq!(1.5, credit);
+
+        let e = count
+            .unmarshal_from(fixture.find("bad").unwrap())
+            .unwrap_err();
+        assert!(matches!(e, UtilError::InvalidValue { value, .. } if value == "4 2"));
+
+        let mut name = String::new();
+        name.unmarshal_from(fixture.find("credit").unwrap())
+            .unwrap();
+        assert_eq!(" 1.5\t", name);
+
+        let mut raw = 0i64;
+        assert!(is_value_from_str(
+            &fixture
+                .find("count")
+                .unwrap()
+                .unmarshal_into(&mut raw)
+                .unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_integer_overflow() {
+        assert!(is_value_from_str(&unmarshal_text::<i8>("128").unwrap_err()));
+        assert!(is_value_from_str(
+            &unmarshal_text::<i16>("32768").unwrap_err()
+        ));
+        assert!(is_value_from_str(
+            &unmarshal_text::<i32>("2147483648").unwrap_err()
+        ));
+        assert!(is_value_from_str(&unmarshal_text::<u8>("-1").unwrap_err()));
+        assert!(is_value_from_str(&unmarshal_text::<u8>("256").unwrap_err()));
+        assert!(is_value_from_str(
+            &unmarshal_text::<u16>("65536").unwrap_err()
+        ));
+        assert!(is_value_from_str(
+            &unmarshal_text::<u32>("4294967296").unwrap_err()
+        ));
+        assert!(is_value_from_str(
+            &unmarshal_text::<u64>("18446744073709551616").unwrap_err()
+        ));
+        assert!(is_value_from_str(
+            &unmarshal_text::<usize>("-1").unwrap_err()
+        ));
+        assert!(is_value_from_str(
+            &unmarshal_text::<isize>("not_a_number").unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_f32() {
+        assert_eq!(0.25f32, unmarshal_text::<f32>("0.25").unwrap());
+        assert_eq!(-12.5f32, unmarshal_text::<f32>("-12.5").unwrap());
+        assert_eq!(1e-3f32, unmarshal_text::<f32>("1e-3").unwrap());
+        assert!(is_value_from_str(
+            &unmarshal_text::<f32>("not_a_number").unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_option() {
+        let mut result = Option::<i64>::None;
+        let written = result
+            .unmarshal_from(&parse_node("<x/>").unwrap().unwrap())
+            .unwrap();
+        assert!(!written);
+        assert_eq!(None, result);
+
+        let written = result
+            .unmarshal_from(&parse_node("<x>5</x>").unwrap().unwrap())
+            .unwrap();
+        assert!(written);
+        assert_eq!(Some(5), result);
+
+        let e = result
+            .unmarshal_from(&parse_node("<x>five</x>").unwrap().unwrap())
+            .unwrap_err();
+        assert!(is_value_from_str(&e));
+        assert_eq!(Some(5), result);
+    }
+
+    #[test]
+    fn test_unmarshal_children_into() {
+        let fixture = parse_node(
+            "<reply><result>1</result><other>x</other><result>2</result><result>3</result></reply>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = vec![0, 1, 2, 3];
+
+        let mut result = vec![0i64];
+        let n = fixture
+            .unmarshal_children_into("result", &mut result)
+            .unwrap();
+
+        assert_eq!(3, n);
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_unmarshal_children_into_no_matches() {
+        let fixture = parse_node("<reply><other>x</other></reply>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = Vec::<i64>::new();
+        let n = fixture
+            .unmarshal_children_into("result", &mut result)
+            .unwrap();
+
+        assert_eq!(0, n);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_unmarshal_children_into_failure() {
+        let fixture =
+            parse_node("<reply><result>1</result><result>x</result><result>3</result></reply>")
+                .unwrap()
+                .unwrap();
+
+        let mut result = vec![7i64];
+        let e = fixture
+            .unmarshal_children_into("result", &mut result)
+            .unwrap_err();
+
+        assert!(is_value_from_str(&e));
+        assert_eq!(Some("result[1]"), e.path());
+        assert_eq!(vec![7], result);
+    }
+
+    #[derive(Debug, Default)]
+    struct FileRef {
+        file_name: String,
+    }
+
+    impl Unmarshaller for FileRef {
+        fn unmarshal_from(&mut self, node: &treexml::Element) -> Result<bool, UtilError> {
+            self.file_name = node
+                .find_value1("file_name")
+                .map_err(|e| e.within(&node.name))?;
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_error_path_nested() {
+        let fixture = parse_node("<reply><result><file_ref/></result></reply>")
+            .unwrap()
+            .unwrap();
+        let expectation = "value not found at result.file_ref.file_name";
+
+        let e = fixture
+            .find_value1::<String, _>("result.file_ref.file_name")
+            .unwrap_err();
+        assert_eq!(expectation, e.to_string());
+
+        let mut result = (FileRef::default(),);
+        let e = result.unmarshal_from(&fixture.children[0]).unwrap_err();
+        assert_eq!(expectation, e.to_string());
+    }
+
+    #[test]
+    fn test_error_path_indexed() {
+        let fixture = parse_node(
+            "<reply><result><file_ref><file_name>a</file_name></file_ref></result><other/><result><file_ref><file_name>b</file_name></file_ref></result><result><file_ref/></result></reply>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut result = Vec::<(FileRef,)>::new();
+        let e = fixture
+            .unmarshal_children_into("result", &mut result)
+            .unwrap_err();
+
+        assert_eq!(
+            "value not found at result[2].file_ref.file_name",
+            e.to_string()
+        );
+
+        let mut result = BTreeSet::<(String, i64)>::new();
+        let e = result
+            .unmarshal_from(
+                &parse_node(
+                    "<pairs><pair><k>a</k><v>1</v></pair><pair><k>b</k><v>x</v></pair></pairs>",
+                )
+                .unwrap()
+                .unwrap(),
+            )
+            .unwrap_err();
+
+        assert!(matches!(e, UtilError::InvalidValue { path, .. } if path == "pairs.pair[1].v"));
+    }
+
+    #[test]
+    fn test_deserialize_string_map() {
+        let fixture = parse_node("<props><timeout>5</timeout><host>x</host><verbose/></props>")
+            .unwrap()
+            .unwrap();
+        let expectation = vec![
+            ("timeout".to_string(), "5".to_string()),
+            ("host".to_string(), "x".to_string()),
+            ("verbose".to_string(), String::new()),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let mut result = HashMap::new();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_deserialize_string_map_duplicate() {
+        let fixture = parse_node("<props><host>x</host><host>y</host></props>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = HashMap::new();
+        assert!(result.unmarshal_from(&fixture).is_err());
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_string_map_nested() {
+        let fixture = parse_node("<props><host><name>x</name></host></props>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = HashMap::new();
+        assert!(result.unmarshal_from(&fixture).is_err());
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_duration() {
+        use std::time::Duration;
+
+        assert_eq!(
+            Duration::from_secs(90),
+            unmarshal_text::<Duration>("90").unwrap()
+        );
+        assert_eq!(
+            Duration::from_millis(250),
+            unmarshal_text::<Duration>("0.25").unwrap()
+        );
+        assert_eq!(Duration::ZERO, unmarshal_text::<Duration>("0").unwrap());
+        for text in &[" -1.50 ", "inf", "1e400"] {
+            let e = unmarshal_text::<Duration>(text).unwrap_err();
+            assert!(
+                matches!(&e, UtilError::InvalidValue { value, .. } if value == text),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_deserialize_addresses() {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+        let v4 = Ipv4Addr::UNSPECIFIED;
+        let v6 = Ipv6Addr::UNSPECIFIED;
+        let ip = IpAddr::V4(v4);
+        let sock = SocketAddr::new(ip, 0);
+
+        assert_eq!(
+            Ipv4Addr::new(192, 168, 0, 1),
+            unmarshal_text_with("192.168.0.1", v4).unwrap()
+        );
+        assert_eq!(Ipv6Addr::LOCALHOST, unmarshal_text_with("::1", v6).unwrap());
+        assert_eq!(
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            unmarshal_text_with("::1", ip).unwrap()
+        );
+        assert_eq!(
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 31416),
+            unmarshal_text_with("[::1]:31416", sock).unwrap()
+        );
+        assert!(is_value_from_str(
+            &unmarshal_text_with("300.1.1.1", ip).unwrap_err()
+        ));
+        assert!(is_value_from_str(
+            &unmarshal_text_with("::1:31416", sock).unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_address_empty() {
+        let fixture = parse_node("<host/>").unwrap().unwrap();
+
+        let mut result = std::net::Ipv4Addr::UNSPECIFIED;
+        let e = result.unmarshal_from(&fixture).unwrap_err();
+
+        assert!(matches!(e, UtilError::InvalidValue { path, .. } if path == "host"));
+
+        let fixture = parse_node("<host><![CDATA[10.0.0.1]]></host>")
+            .unwrap()
+            .unwrap();
+        assert!(result.unmarshal_from(&fixture).unwrap());
+        assert_eq!(std::net::Ipv4Addr::new(10, 0, 0, 1), result);
+    }
+
+    #[test]
+    fn test_deserialize_path() {
+        use std::path::PathBuf;
+
+        assert_eq!(
+            PathBuf::from("/var/lib/boinc data/projects "),
+            unmarshal_text::<PathBuf>("/var/lib/boinc data/projects ").unwrap()
+        );
+
+        let fixture = parse_node("<project_dir/>").unwrap().unwrap();
+        let mut result = PathBuf::from("/default");
+        assert!(!result.unmarshal_from(&fixture).unwrap());
+        assert_eq!(PathBuf::from("/default"), result);
+    }
+
+    fn roundtrip<T>(s: &str) -> (treexml::Element, treexml::Element)
+    where
+        T: Unmarshaller + Marshaller + Default,
+    {
+        let fixture = parse_node(s).unwrap().unwrap();
+
+        let mut v = T::default();
+        v.unmarshal_from(&fixture).unwrap();
+
+        let result = v.marshal_into(&fixture.name);
+
+        (fixture, result)
+    }
+
+    #[test]
+    fn test_marshal_roundtrip() {
+        for s in &["<data>true</data>", "<data>false</data>"] {
+            let (expectation, result) = roundtrip::<bool>(s);
+            assert_eq!(expectation, result);
+        }
+
+        let (expectation, result) = roundtrip::<i64>("<data>-42</data>");
+        assert_eq!(expectation, result);
+
+        let (expectation, result) = roundtrip::<f64>("<data>0.25</data>");
+        assert_eq!(expectation, result);
+
+        let (expectation, result) = roundtrip::<String>("<data>hello</data>");
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_marshal_bool_tokens() {
+        for &v in &[true, false] {
+            let root = make_tree_element("root", vec![v.marshal_into("flag")]);
+
+            assert_eq!(v, root.find_bool("flag").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_cdata_only() {
+        assert_eq!('x', unmarshal_cdata::<char>("x").unwrap());
+        assert_eq!(
+            std::path::PathBuf::from("/var/lib/boinc"),
+            unmarshal_cdata::<std::path::PathBuf>("/var/lib/boinc").unwrap()
+        );
+        assert_eq!([1, 2], unmarshal_cdata::<[i32; 2]>("1 2").unwrap());
+        assert_eq!(
+            vec![0xab, 0xcd],
+            unmarshal_cdata::<HexBytes>("abcd").unwrap().0
+        );
+        assert!(!unmarshal_cdata::<bool>("false").unwrap());
+
+        let fixture = parse_node("<env><HOME><![CDATA[/home/boinc]]></HOME></env>")
+            .unwrap()
+            .unwrap();
+        let mut result = HashMap::<String, String>::new();
+        assert!(result.unmarshal_from(&fixture).unwrap());
+        assert_eq!(Some("/home/boinc"), result.get("HOME").map(String::as_str));
+    }
+
+    #[test]
+    fn test_find_bool_cdata() {
+        let fixture = parse_node("<prefs><a><![CDATA[false]]></a><b><![CDATA[ yes ]]></b></prefs>")
+            .unwrap()
+            .unwrap();
+
+        assert!(!fixture.find_bool("a").unwrap());
+        assert!(fixture.find_bool("b").unwrap());
+        assert_eq!(Some(false), fixture.find_bool0("a").unwrap());
+        assert!(matches!(
+            fixture.find_bool_strict("a"),
+            Err(UtilError::InvalidFlag { path }) if path == "a"
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_deserialize_datetime_cdata() {
+        use chrono::{DateTime, TimeZone, Utc};
+
+        assert_eq!(
+            Utc.with_ymd_and_hms(2020, 1, 2, 3, 4, 5).unwrap(),
+            unmarshal_cdata::<DateTime<Utc>>("2020-01-02T03:04:05Z").unwrap()
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_deserialize_uuid_cdata() {
+        assert_eq!(
+            uuid::Uuid::from_u128(0x550e8400_e29b_41d4_a716_446655440000),
+            unmarshal_cdata::<uuid::Uuid>("550e8400-e29b-41d4-a716-446655440000").unwrap()
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_deserialize_url_cdata() {
+        assert_eq!(
+            url::Url::parse("https://example.com/?a=1&b=2").unwrap(),
+            unmarshal_cdata_with(
+                "https://example.com/?a=1&b=2",
+                url::Url::parse("http://x/").unwrap()
+            )
+            .unwrap()
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_cdata() {
+        assert_eq!(
+            b"hello".to_vec(),
+            unmarshal_cdata::<Base64Bytes>("aGVsbG8=").unwrap().0
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_deserialize_datetime() {
+        use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+        let expectation = Utc.with_ymd_and_hms(2020, 1, 2, 3, 4, 5).unwrap();
+
+        assert_eq!(
+            expectation,
+            unmarshal_text::<DateTime<Utc>>("2020-01-02T03:04:05Z").unwrap()
+        );
+        assert_eq!(
+            expectation,
+            unmarshal_text::<DateTime<Utc>>("2020-01-02T05:04:05+02:00").unwrap()
+        );
+        assert_eq!(
+            expectation,
+            unmarshal_text::<DateTime<Utc>>("1577934245").unwrap()
+        );
+        assert_eq!(
+            expectation + chrono::Duration::milliseconds(500),
+            unmarshal_text::<DateTime<Utc>>("1577934245.5").unwrap()
+        );
+        assert_eq!(
+            expectation.naive_utc(),
+            unmarshal_text::<NaiveDateTime>("1577934245").unwrap()
+        );
+        assert!(unmarshal_text::<DateTime<Utc>>("02/01/2020").is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_find_datetime() {
+        use chrono::{TimeZone, Utc};
+
+        let fixture = parse_node(
+            "<reply><sent>2020-01-02T03:04:05Z</sent><received>1577934245</received><bad>yesterday</bad></reply>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = Utc.with_ymd_and_hms(2020, 1, 2, 3, 4, 5).unwrap();
+
+        assert_eq!(expectation, fixture.find_datetime("sent").unwrap());
+        assert_eq!(expectation, fixture.find_datetime("received").unwrap());
+
+        let e = fixture.find_datetime("bad").unwrap_err();
+        assert!(matches!(e, UtilError::InvalidValue { path, .. } if path == "bad"));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_deserialize_uuid() {
+        let expectation = uuid::Uuid::from_u128(0x550e8400_e29b_41d4_a716_446655440000);
+
+        for text in &[
+            "550e8400-e29b-41d4-a716-446655440000",
+            "550e8400e29b41d4a716446655440000",
+            "{550e8400-e29b-41d4-a716-446655440000}",
+        ] {
+            assert_eq!(expectation, unmarshal_text::<uuid::Uuid>(text).unwrap());
+        }
+
+        let e = unmarshal_text::<uuid::Uuid>("550e8400-nope").unwrap_err();
+        assert!(matches!(e, UtilError::InvalidValue { value, .. } if value == "550e8400-nope"));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_find_uuid() {
+        let fixture =
+            parse_node("<job><job_uuid>550e8400-e29b-41d4-a716-446655440000</job_uuid></job>")
+                .unwrap()
+                .unwrap();
+        let expectation = uuid::Uuid::from_u128(0x550e8400_e29b_41d4_a716_446655440000);
+
+        assert_eq!(expectation, fixture.find_uuid("job_uuid").unwrap());
+        assert!(fixture.find_uuid("missing").is_err());
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_find_url() {
+        let fixture = parse_node(
+            "<project><master_url>\n  http://example.com/\n</master_url><relative>/boinc/</relative><bad>ht tp://example.com/</bad></project>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = url::Url::parse("http://example.com/").unwrap();
+
+        assert_eq!(expectation, fixture.find_url("master_url").unwrap());
+
+        let e = fixture.find_url("relative").unwrap_err();
+        assert!(matches!(
+            e,
+            UtilError::InvalidUrl { path, reason: url::ParseError::RelativeUrlWithoutBase, .. }
+                if path == "relative"
+        ));
+
+        assert!(fixture.find_url("bad").is_err());
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_deserialize_url() {
+        let fixture = parse_node("<master_url> https://example.com/project/ </master_url>")
+            .unwrap()
+            .unwrap();
+        let expectation = url::Url::parse("https://example.com/project/").unwrap();
+
+        let mut result = url::Url::parse("http://localhost/").unwrap();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_deserialize_char() {
+        assert_eq!(',', unmarshal_text::<char>(",").unwrap());
+        assert_eq!('é', unmarshal_text::<char>("é").unwrap());
+
+        let e = unmarshal_text::<char>("").unwrap_err();
+        assert!(is_value_from_str(&e));
+
+        let e = unmarshal_text::<char>("ab").unwrap_err();
+        assert!(matches!(e, UtilError::InvalidValue { value, .. } if value == "ab"));
+    }
+
+    #[test]
+    fn test_deserialize_non_zero() {
+        use std::num::{NonZeroI32, NonZeroU16, NonZeroU64};
+
+        let port = NonZeroU16::new(1).unwrap();
+        assert_eq!(
+            NonZeroU16::new(31416).unwrap(),
+            unmarshal_text_with("31416", port).unwrap()
+        );
+
+        let e = unmarshal_text_with("0", port).unwrap_err();
+        assert!(
+            matches!(&e, UtilError::ZeroValue { path, value } if path == "data" && value == "0")
+        );
+        assert_eq!(
+            r#"zero value "0" at data, expected a non-zero number"#,
+            e.to_string()
+        );
+
+        let e = unmarshal_text_with("65536", port).unwrap_err();
+        assert!(is_value_from_str(&e));
+
+        assert_eq!(
+            NonZeroI32::new(-5).unwrap(),
+            unmarshal_text_with("-5", NonZeroI32::new(1).unwrap()).unwrap()
+        );
+        assert!(matches!(
+            unmarshal_text_with("0", NonZeroU64::new(1).unwrap()),
+            Err(UtilError::ZeroValue { .. })
+        ));
+
+        let fixture = parse_node("<x> 00 </x>").unwrap().unwrap();
+        let mut result = port;
+        let e = result.unmarshal_from(&fixture).unwrap_err();
+        assert!(
+            matches!(&e, UtilError::ZeroValue { path, value } if path == "x" && value == " 00 ")
+        );
+        assert_eq!(port, result);
+    }
+
+    #[test]
+    fn test_deserialize_array() {
+        assert_eq!(
+            [255.0, 128.0, 0.5],
+            unmarshal_text::<[f64; 3]>("255 128\n 0.5").unwrap()
+        );
+        assert_eq!([-1, 2], unmarshal_text::<[i64; 2]>("-1 2").unwrap());
+
+        let e = unmarshal_text::<[i64; 3]>("1 2").unwrap_err();
+        assert!(matches!(
+            e,
+            UtilError::WrongCount {
+                expected: 3,
+                found: 2,
+                ..
+            }
+        ));
+
+        let e = unmarshal_text::<[i64; 2]>("1 2 3").unwrap_err();
+        assert!(matches!(
+            e,
+            UtilError::WrongCount {
+                expected: 2,
+                found: 3,
+                ..
+            }
+        ));
+
+        assert!(is_value_from_str(
+            &unmarshal_text::<[i64; 2]>("1 x").unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_sets() {
+        let fixture = parse_node("<tags><tag>b</tag><tag>a</tag><tag>b</tag></tags>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = BTreeSet::<String>::new();
+        result.unmarshal_from(&fixture).unwrap();
+        assert_eq!(
+            vec!["a".to_string(), "b".to_string()],
+            result.into_iter().collect::<Vec<_>>()
+        );
+
+        let mut result = HashSet::<String>::new();
+        result.unmarshal_from(&fixture).unwrap();
+        assert_eq!(2, result.len());
+        assert!(result.contains("a") && result.contains("b"));
+    }
+
+    #[test]
+    fn test_deserialize_btree_map() {
+        let fixture = parse_node("<limits><ram>8</ram><cpus>4</cpus><disk>100</disk></limits>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = BTreeMap::<String, i64>::new();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(
+            vec![
+                ("cpus".to_string(), 4),
+                ("disk".to_string(), 100),
+                ("ram".to_string(), 8)
+            ],
+            result.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_btree_map_duplicate() {
+        let fixture = parse_node("<limits><ram>8</ram><ram>16</ram></limits>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = BTreeMap::<String, i64>::new();
+        let e = result.unmarshal_from(&fixture).unwrap_err();
+
+        assert!(matches!(e, UtilError::DuplicateKey { key, .. } if key == "ram"));
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_128_bit() {
+        assert_eq!(
+            u128::MAX,
+            unmarshal_text::<u128>("340282366920938463463374607431768211455").unwrap()
+        );
+        assert!(is_value_from_str(
+            &unmarshal_text::<u128>("340282366920938463463374607431768211456").unwrap_err()
+        ));
+        assert_eq!(
+            i128::MIN,
+            unmarshal_text::<i128>("-170141183460469231731687303715884105728").unwrap()
+        );
+        assert!(is_value_from_str(
+            &unmarshal_text::<i128>("170141183460469231731687303715884105728").unwrap_err()
+        ));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_roundtrip() {
+        let mut seed = 0x2545_f491u32;
+        let expectation = (0..300)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect::<Vec<_>>();
+
+        let fixture = make_base64_element("blob", &expectation);
+
+        let mut result = Base64Bytes::default();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(expectation, result.0);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_wrapped() {
+        let fixture = parse_node("<blob>\n  aGVsbG8g\n  d29ybGQ=\n</blob>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = Base64Bytes::default();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(b"hello world".to_vec(), result.0);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_invalid_padding() {
+        let e = unmarshal_text::<Base64Bytes>("aGVsbG8=g").unwrap_err();
+        assert!(is_value_from_str(&e));
+
+        let e = unmarshal_text::<Base64Bytes>("aGVsbA=").unwrap_err();
+        assert!(is_value_from_str(&e));
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let expectation = vec![0xd4, 0x1d, 0x8c, 0xd9, 0x8f, 0x00, 0xb2, 0x04];
+
+        let fixture = make_hex_element("md5_cksum", &expectation);
+        assert_eq!(Some("d41d8cd98f00b204".to_string()), fixture.text);
+
+        let mut result = HexBytes::default();
+        result.unmarshal_from(&fixture).unwrap();
+
+        assert_eq!(expectation, result.0);
+    }
+
+    #[test]
+    fn test_hex_prefix_and_case() {
+        assert_eq!(
+            vec![0xde, 0xad, 0xbe, 0xef],
+            unmarshal_text::<HexBytes>("0xDeadBEEF").unwrap().0
+        );
+        assert!(unmarshal_text::<HexBytes>("").unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_hex_errors() {
+        let e = unmarshal_text::<HexBytes>("abc").unwrap_err();
+        assert!(matches!(
+            e,
+            UtilError::InvalidHex {
+                source: HexError::OddLength { len: 3 },
+                ..
+            }
+        ));
+        assert!(e.to_string().contains("odd"));
+
+        let e = unmarshal_text::<HexBytes>("abcg").unwrap_err();
+        let expectation = HexError::InvalidCharacter {
+            character: 'g',
+            position: 3,
+        };
+        assert!(
+            matches!(&e, UtilError::InvalidHex { source, value, .. } if *source == expectation && value == "abcg")
+        );
+        assert!(e.to_string().contains("position 3"));
+
+        let e = unmarshal_text::<HexBytes>("0x0z").unwrap_err();
+        let expectation = HexError::InvalidCharacter {
+            character: 'z',
+            position: 3,
+        };
+        assert!(matches!(&e, UtilError::InvalidHex { source, .. } if *source == expectation));
+        assert!(e.to_string().contains("position 3"));
+    }
+
+    #[test]
+    fn test_deserialize_tuple() {
+        let fixture = parse_node("<range><min>1</min><max>5</max></range>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = (0i64, 0i64);
+        result.unmarshal_from(&fixture).unwrap();
+        assert_eq!((1, 5), result);
+
+        let fixture = parse_node("<entry><name>x</name><size>2.5</size><active/></entry>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = (String::new(), 0f64, false);
+        result.unmarshal_from(&fixture).unwrap();
+        assert_eq!(("x".to_string(), 2.5, true), result);
+    }
+
+    #[test]
+    fn test_deserialize_tuple_mismatch() {
+        let fixture = parse_node("<range><min>1</min><mid>3</mid><max>5</max></range>")
+            .unwrap()
+            .unwrap();
+
+        let mut result = (0i64, 0i64);
+        let e = result.unmarshal_from(&fixture).unwrap_err();
+
+        assert!(matches!(
+            e,
+            UtilError::WrongCount {
+                expected: 2,
+                found: 3,
+                ..
+            }
+        ));
+        assert_eq!((0, 0), result);
+    }
+
+    #[test]
+    fn test_unmarshal_fields() {
+        let fixture = parse_node(
+            "<project><name>x</name><unknown>?</unknown><suspended/><share>100</share></project>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut name = String::new();
+        let mut suspended = false;
+        let mut share = 0i64;
+        let mut detached = false;
+
+        let n = fixture
+            .unmarshal_fields(&mut [
+                ("name", &mut name),
+                ("suspended", &mut suspended),
+                ("share", &mut share),
+                ("detached", &mut detached),
+            ])
+            .unwrap();
+
+        assert_eq!(3, n);
+        assert_eq!("x", name);
+        assert!(suspended);
+        assert_eq!(100, share);
+        assert!(!detached);
+    }
+
+    #[test]
+    fn test_unmarshal_fields_presence() {
+        let fixture = parse_node(
+            "<project><name>x</name><project_suspended><junk>1</junk></project_suspended></project>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut name = String::new();
+        let n = fixture
+            .unmarshal_fields(&mut [("name", &mut name), ("project_suspended", &mut ())])
+            .unwrap();
+        assert_eq!(2, n);
+        assert_eq!("x", name);
+
+        let n = fixture
+            .unmarshal_fields(&mut [("name", &mut name), ("dont_request_more_work", &mut ())])
+            .unwrap();
+        assert_eq!(1, n);
+    }
+
+    #[test]
+    fn test_count_children() {
+        let fixture = parse_node(
+            "<reply><result><file><ref/></file><file><ref/><ref/></file></result><result/><app/></reply>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(0, fixture.count_children("missing"));
+        assert_eq!(1, fixture.count_children("app"));
+        assert_eq!(2, fixture.count_children("result"));
+
+        assert_eq!(0, fixture.count_matching("result.missing"));
+        assert_eq!(2, fixture.count_matching("result.file"));
+        assert_eq!(3, fixture.count_matching("result.file.ref"));
+        assert_eq!(2, fixture.count_matching("result[0].file[1].ref"));
+        assert_eq!(0, fixture.count_matching("result..file"));
+    }
+
+    #[test]
+    fn test_is_empty_element() {
+        let fixture = parse_node(r#"<a><e/><t>x</t><c><![CDATA[y]]></c><k v="1"/><p><e/></p></a>"#)
+            .unwrap()
+            .unwrap();
+        let expectation = [true, false, false, false, false];
+
+        let result: Vec<bool> = fixture
+            .children
+            .iter()
+            .map(|c| c.is_empty_element())
+            .collect();
+
+        assert_eq!(expectation.to_vec(), result);
+    }
+
+    #[test]
+    fn test_has_child() {
+        let fixture = parse_node("<a><b><c/></b><b/></a>").unwrap().unwrap();
+
+        assert!(fixture.has_child("b"));
+        assert!(fixture.has_child("b.c"));
+        assert!(!fixture.has_child("b.d"));
+        assert!(!fixture.has_child("x"));
+        assert!(!fixture.has_child("b..c"));
+    }
+
+    #[test]
+    fn test_unmarshal_fields_error() {
+        let fixture = parse_node("<project><share>lots</share></project>")
+            .unwrap()
+            .unwrap();
+
+        let mut share = 0i64;
+        let e = fixture
+            .unmarshal_fields(&mut [("share", &mut share)])
+            .unwrap_err();
+
+        match e {
+            UtilError::Field { field, source } => {
+                assert_eq!("share", field);
+                assert!(is_value_from_str(&source));
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum State {
+        Active,
+        Suspended,
+    }
+
+    const STATES: &[(&str, State)] = &[("active", State::Active), ("suspended", State::Suspended)];
+
+    #[test]
+    fn test_find_enum() {
+        let fixture = parse_node("<task><state>suspended</state><mode>ACTIVE</mode></task>")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            State::Suspended,
+            fixture.find_enum("state", STATES, false).unwrap()
+        );
+        assert_eq!(
+            State::Active,
+            fixture.find_enum("mode", STATES, true).unwrap()
+        );
+
+        let e = fixture.find_enum("mode", STATES, false).unwrap_err();
+        assert!(
+            matches!(e, UtilError::UnknownVariant { expected, .. } if expected == "active, suspended")
+        );
+    }
+
+    #[test]
+    fn test_find_value_all() {
+        let fixture = parse_node(
+            "<host><coprocs><gpu>1</gpu><cpu>9</cpu><gpu>2</gpu><gpu>3</gpu></coprocs></host>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            vec![1, 2, 3],
+            fixture.find_value_all::<i64, _>("coprocs/gpu").unwrap()
+        );
+        assert!(fixture
+            .find_value_all::<i64, _>("coprocs/fpga")
+            .unwrap()
+            .is_empty());
+        assert!(fixture
+            .find_value_all::<i64, _>("missing/gpu")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_find_value_unique() {
+        let fixture = parse_node(
+            "<app><name>setiathome</name><user><name>a</name></user><user><name>b</name></user></app>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            "setiathome",
+            fixture.find_value_unique1::<String, _>("name").unwrap()
+        );
+
+        let e = fixture
+            .find_value_unique0::<String, _>("user.name")
+            .unwrap_err();
+        assert!(matches!(
+            &e,
+            UtilError::AmbiguousPath { path, count: 2 } if path == "user.name"
+        ));
+        assert_eq!("ambiguous path user.name: 2 matches", e.to_string());
+
+        assert_eq!(
+            None,
+            fixture.find_value_unique0::<String, _>("version").unwrap()
+        );
+        assert!(matches!(
+            fixture.find_value_unique1::<String, _>("version"),
+            Err(UtilError::ValueNotFound { path }) if path == "version"
+        ));
+    }
+
+    #[test]
+    fn test_find_value_path() {
+        let fixture = parse_node(
+            "<reply><result><name>a</name><cpu>1</cpu></result><result><name>b</name><cpu>x</cpu></result></reply>",
+        )
+        .unwrap()
+        .unwrap();
+        let name = Path::parse("name").unwrap();
+        let cpu = Path::parse("cpu").unwrap();
+        let gpu = Path::parse("gpu").unwrap();
+
+        let results = fixture.children_named("result").collect::<Vec<_>>();
+
+        assert_eq!(
+            vec!["a".to_string(), "b".to_string()],
+            results
+                .iter()
+                .map(|r| r.find_value1_path::<String>(&name).unwrap())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(1), results[0].find_value0_path::<i64>(&cpu).unwrap());
+        assert!(is_value_from_str(
+            &results[1].find_value0_path::<i64>(&cpu).unwrap_err()
+        ));
+        assert_eq!(None, results[0].find_value0_path::<i64>(&gpu).unwrap());
+        assert!(matches!(
+            results[0].find_value1_path::<i64>(&gpu),
+            Err(UtilError::ValueNotFound { path }) if path == "gpu"
+        ));
+        assert_eq!(
+            vec!["a".to_string(), "b".to_string()],
+            fixture
+                .find_value_all_path::<String>(&Path::parse("result.name").unwrap())
+                .unwrap()
+        );
+        assert_eq!(
+            Some(results[1]),
+            fixture.find_path(&Path::parse("result[1]").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_find_value_all_failure() {
+        let fixture = parse_node("<coprocs><gpu>1</gpu><gpu>two</gpu><gpu>3</gpu></coprocs>")
+            .unwrap()
+            .unwrap();
+
+        let e = fixture.find_value_all::<i64, _>("gpu").unwrap_err();
+
+        assert!(matches!(e, UtilError::InvalidValue { path, .. } if path == "gpu[1]"));
+    }
+
+    #[test]
+    fn test_attrs_as() {
+        let fixture = parse_node(r#"<coproc count="2" ram="8192"/>"#)
+            .unwrap()
+            .unwrap();
+        let expectation = HashMap::from([("count".to_string(), 2u64), ("ram".to_string(), 8192)]);
+
+        let result = fixture.attrs_as::<u64>().unwrap();
+
+        assert_eq!(expectation, result);
+        assert_eq!(&fixture.attributes, fixture.attrs_str());
+    }
+
+    #[test]
+    fn test_attrs_as_errors() {
+        let fixture = parse_node(r#"<coproc count="2" type="CUDA"/>"#)
+            .unwrap()
+            .unwrap();
+
+        let e = fixture.attrs_as::<u64>().unwrap_err();
+        assert_eq!(r#"invalid value "CUDA" for attribute type"#, e.to_string());
+        assert_eq!(
+            r#"invalid value "CUDA" for attribute type at coprocs"#,
+            e.within("coprocs").to_string()
+        );
+
+        let fixture = treexml::Element::new("empty");
+        assert!(fixture.attrs_as::<u64>().unwrap().is_empty());
+        assert!(fixture.attrs_str().is_empty());
+    }
+
+    #[test]
+    fn test_unmarshal_attr() {
+        let fixture = parse_node(
+            r#"<app active=" Yes " name=" setiathome " count=" 3 " offset="-7" ratio="0.5"/>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut active = false;
+        assert!(active.unmarshal_attr(&fixture, "active").unwrap());
+        assert!(active);
+
+        let mut name = String::new();
+        assert!(name.unmarshal_attr(&fixture, "name").unwrap());
+        assert_eq!(" setiathome ", name);
+
+        let mut count = 0u32;
+        assert!(count.unmarshal_attr(&fixture, "count").unwrap());
+        assert_eq!(3, count);
+
+        let mut offset = 0i64;
+        assert!(offset.unmarshal_attr(&fixture, "offset").unwrap());
+        assert_eq!(-7, offset);
+
+        let mut ratio = 0f64;
+        assert!(ratio.unmarshal_attr(&fixture, "ratio").unwrap());
+        assert_eq!(0.5, ratio);
+    }
+
+    #[test]
+    fn test_unmarshal_attr_absent_and_invalid() {
+        let fixture = parse_node(r#"<app count="many" active="maybe"/>"#)
+            .unwrap()
+            .unwrap();
+
+        let mut name = String::from("unchanged");
+        assert!(!name.unmarshal_attr(&fixture, "name").unwrap());
+        assert_eq!("unchanged", name);
+
+        let mut active = true;
+        assert!(!active.unmarshal_attr(&fixture, "suspended").unwrap());
+        assert!(active);
+
+        let mut count = 1u32;
+        let e = count.unmarshal_attr(&fixture, "count").unwrap_err();
+        assert_eq!(r#"invalid value "many" for attribute count"#, e.to_string());
+        assert_eq!(1, count);
+        assert!(active.unmarshal_attr(&fixture, "active").is_err());
+    }
+
+    #[test]
+    fn test_find_value_attr_or_child() {
+        let fixture = parse_node(
+            r#"<files><file name="a"/><file><name>b</name></file><file name="c"><name>d</name></file><file/></files>"#,
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = vec![
+            Some("a".to_string()),
+            Some("b".into()),
+            Some("c".into()),
+            None,
+        ];
+
+        let result = fixture
+            .children
+            .iter()
+            .map(|f| f.find_value_attr_or_child::<String>("name"))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(expectation, result);
+        assert_eq!(
+            "b",
+            fixture.children[1]
+                .find_value_attr_or_child1::<String>("name")
+                .unwrap()
+        );
+        let e = fixture.children[3]
+            .find_value_attr_or_child1::<String>("name")
+            .unwrap_err();
+        assert!(matches!(e, UtilError::ValueNotFound { path } if path == "name"));
+    }
+
+    #[test]
+    fn test_find_value_attr_or_child_invalid() {
+        let fixture = parse_node(r#"<file size="big"><count>x</count></file>"#)
+            .unwrap()
+            .unwrap();
+
+        let e = fixture.find_value_attr_or_child::<u64>("size").unwrap_err();
+        assert!(matches!(e, UtilError::InvalidAttribute { name, .. } if name == "size"));
+        let e = fixture
+            .find_value_attr_or_child::<u64>("count")
+            .unwrap_err();
+        assert_eq!(Some("count"), e.path());
+    }
+
+    #[test]
+    fn test_validate_required() {
+        let fixture =
+            parse_node("<project><name>p</name><url> </url><app><id>1</id></app></project>")
+                .unwrap()
+                .unwrap();
+
+        assert!(fixture
+            .validate_required(&["name", "url", "app.id"])
+            .is_ok());
+
+        let errors = fixture
+            .validate_required(&["name", "master_url", "app.version", "app"])
+            .unwrap_err();
+        let result = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        let expectation = vec![
+            "element not found at master_url",
+            "element not found at app.version",
+        ];
+        assert_eq!(expectation, result);
+
+        let errors = fixture
+            .validate_required_with(&["name", "url", "app", "missing", "a..b"], true)
+            .unwrap_err();
+        assert_eq!(4, errors.len());
+        assert!(matches!(&errors[0], UtilError::ValueNotFound { path } if path == "url"));
+        assert!(matches!(&errors[1], UtilError::ValueNotFound { path } if path == "app"));
+        assert!(matches!(&errors[2], UtilError::ElementNotFound { path } if path == "missing"));
+        assert!(matches!(&errors[3], UtilError::InvalidPath { .. }));
+    }
+
+    #[test]
+    fn test_validate_types() {
+        let fixture = parse_node(
+            "<app><id> 7 </id><ratio>0.5</ratio><beta>maybe</beta><name>x</name><count>many</count><flag/><size/></app>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let specs = [
+            ("id", ValueKind::Int),
+            ("ratio", ValueKind::Float),
+            ("flag", ValueKind::Bool),
+            ("name", ValueKind::String),
+            ("missing", ValueKind::Int),
+        ];
+        assert!(fixture.validate_types(&specs).is_ok());
+
+        let specs = [
+            ("id", ValueKind::Int),
+            ("ratio", ValueKind::Int),
+            ("beta", ValueKind::Bool),
+            ("count", ValueKind::Float),
+            ("size", ValueKind::Int),
+        ];
+        let errors = fixture.validate_types(&specs).unwrap_err();
+        let result = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        let expectation = vec![
+            r#"invalid value "0.5" at ratio"#,
+            r#"invalid boolean "maybe" at beta"#,
+            r#"invalid value "many" at count"#,
+            "value not found at size",
+        ];
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_find_attr_value() {
+        let fixture = parse_node(r#"<tasks><task id="5" priority="high"/></tasks>"#)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            Some(5),
+            fixture.find_attr_value0::<i64, _>("task", "id").unwrap()
+        );
+        assert_eq!(
+            "high",
+            fixture
+                .find_attr_value1::<String, _>("task", "priority")
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            fixture
+                .find_attr_value0::<i64, _>("task", "deadline")
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            fixture.find_attr_value0::<i64, _>("missing", "id").unwrap()
+        );
+        assert!(matches!(
+            fixture.find_attr_value0::<i64, _>("task", "priority"),
+            Err(UtilError::InvalidAttribute { path, name, .. }) if path == "task" && name == "priority"
+        ));
+
+        let task = fixture.find("task").unwrap();
+        assert_eq!(Some(5), task.find_attr_value0::<i64, _>("", "id").unwrap());
+
+        let e = fixture
+            .find_attr_value1::<i64, _>("task", "deadline")
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            UtilError::AttributeNotFound { path, name } if path == "task" && name == "deadline"
+        ));
+    }
+
+    #[test]
+    fn test_find_bool_tokens() {
+        let fixture = parse_node("<prefs><a>YES</a><b>Off</b><c>True</c><d/><e>maybe</e></prefs>")
+            .unwrap()
+            .unwrap();
+
+        assert!(fixture.find_bool("a").unwrap());
+        assert!(!fixture.find_bool("b").unwrap());
+        assert!(fixture.find_bool("c").unwrap());
+        assert!(fixture.find_bool("d").unwrap());
+        assert!(!fixture.find_bool("missing").unwrap());
+        assert!(fixture.find_bool("e").is_err());
+    }
+
+    #[test]
+    fn test_find_bool0() {
+        let fixture = parse_node("<prefs><x/><y>0</y><z>maybe</z></prefs>")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(None, fixture.find_bool0("missing").unwrap());
+        assert_eq!(Some(true), fixture.find_bool0("x").unwrap());
+        assert_eq!(Some(false), fixture.find_bool0("y").unwrap());
+        let e = fixture.find_bool0("z").unwrap_err();
+        assert!(
+            matches!(e, UtilError::InvalidBool { path, token } if path == "z" && token == "maybe")
+        );
+    }
+
+    #[test]
+    fn test_bool_tokens_agree() {
+        let fixture = parse_node("<f><a>1</a><b> true </b><c>yes</c><d>Off</d><e>maybe</e></f>")
+            .unwrap()
+            .unwrap();
+
+        for (name, expectation) in [("a", true), ("b", true), ("c", true), ("d", false)] {
+            let mut result = !expectation;
+            let child = fixture.find_child(|c| c.name == name).unwrap();
+            assert!(result.unmarshal_from(child).unwrap());
+            assert_eq!(expectation, result, "{}", name);
+            assert_eq!(expectation, fixture.find_bool(name).unwrap(), "{}", name);
+        }
+
+        let mut result = false;
+        let child = fixture.find_child(|c| c.name == "e").unwrap();
+        assert!(child.unmarshal_bool_into(&mut result).is_err());
+        assert!(fixture.find_bool("e").is_err());
+    }
+
+    #[test]
+    fn test_find_bool_strict() {
+        let fixture =
+            parse_node("<prefs><a><unexpected/></a><b><![CDATA[1]]></b><c/><d>no</d></prefs>")
+                .unwrap()
+                .unwrap();
+
+        assert!(fixture.find_bool("a").unwrap());
+        assert!(fixture.find_bool("b").unwrap());
+
+        let e = fixture.find_bool_strict("a").unwrap_err();
+        assert!(matches!(e, UtilError::InvalidFlag { path } if path == "a"));
+        assert!(fixture.find_bool_strict("b").is_err());
+        assert!(fixture.find_bool_strict("c").unwrap());
+        assert!(!fixture.find_bool_strict("d").unwrap());
+        assert!(!fixture.find_bool_strict("missing").unwrap());
+    }
+
+    #[test]
+    fn test_find_bool_with() {
+        let fixture = parse_node("<prefs><a>Y</a><b>n</b><c>yes</c></prefs>")
+            .unwrap()
+            .unwrap();
+
+        assert!(fixture.find_bool_with("a", &["y"], &["n"]).unwrap());
+        assert!(!fixture.find_bool_with("b", &["y"], &["n"]).unwrap());
+        assert!(fixture.find_bool_with("c", &["y"], &["n"]).is_err());
+
+        let e = fixture
+            .find_bool_with("a", &["y", "x"], &["n", "X"])
+            .unwrap_err();
+        assert!(matches!(e, UtilError::AmbiguousBoolToken { token } if token == "x"));
+    }
+
+    #[test]
+    fn test_cdata_fallback() {
+        let fixture = parse_node(
+            "<app><desc><![CDATA[stuff & things]]></desc><name>x</name><both>text<![CDATA[cdata]]></both><n><![CDATA[5]]></n></app>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            Some("stuff & things".to_string()),
+            fixture.find_cdata0("desc").unwrap()
+        );
+        assert_eq!(None, fixture.find_cdata0("name").unwrap());
+        assert!(fixture.find_cdata1("name").is_err());
+        assert_eq!("cdata", fixture.find_cdata1("both").unwrap());
+
+        assert_eq!(
+            "stuff & things",
+            fixture.find_value1::<String, _>("desc").unwrap()
+        );
+        assert_eq!("x", fixture.find_value1::<String, _>("name").unwrap());
+        assert_eq!("text", fixture.find_value1::<String, _>("both").unwrap());
+
+        let mut result = 0i64;
+        assert!(result.unmarshal_from(fixture.find("n").unwrap()).unwrap());
+        assert_eq!(5, result);
+    }
+
+    #[test]
+    fn test_text_content() {
+        let mut fixture = make_tree_element("desc", vec![]);
+        assert_eq!(None, fixture.text_content());
+        assert_eq!(None, fixture.text_content_trimmed());
+
+        fixture.text = Some(" text ".into());
+        assert_eq!(Some(" text ".to_string()), fixture.text_content());
+        assert_eq!(Some("text".to_string()), fixture.text_content_trimmed());
+
+        fixture.cdata = Some("cdata ".into());
+        assert_eq!(Some(" text cdata ".to_string()), fixture.text_content());
+        assert_eq!(
+            Some("text cdata".to_string()),
+            fixture.text_content_trimmed()
+        );
+
+        fixture.text = None;
+        assert_eq!(Some("cdata ".to_string()), fixture.text_content());
+        assert_eq!(Some("cdata".to_string()), fixture.text_content_trimmed());
+
+        fixture.cdata = Some(" \n ".into());
+        assert_eq!(None, fixture.text_content_trimmed());
+    }
+
+    #[test]
+    fn test_trimmed_nonempty() {
+        for (fixture, expectation) in &[
+            (None, None),
+            (Some(""), None),
+            (Some(" \n\t "), None),
+            (Some("  a b \n"), Some("a b")),
+            (Some("a b"), Some("a b")),
+        ] {
+            let fixture = fixture.map(String::from);
+
+            assert_eq!(*expectation, trimmed_str(&fixture));
+            assert_eq!(expectation.map(String::from), trimmed_nonempty(&fixture));
+        }
+    }
+
+    #[test]
+    fn test_find_value_or() {
+        let fixture = parse_node("<prefs><ncpus>4</ncpus><ram>lots</ram></prefs>")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(4, fixture.find_value_or("ncpus", 1i64).unwrap());
+        assert_eq!(1, fixture.find_value_or("missing", 1i64).unwrap());
+        assert!(fixture.find_value_or("ram", 1i64).is_err());
+
+        assert_eq!(4, fixture.find_value_or_else("ncpus", || 1i64).unwrap());
+        assert_eq!(2, fixture.find_value_or_else("missing", || 2i64).unwrap());
+        assert!(fixture.find_value_or_else("ram", || 1i64).is_err());
+    }
+
+    #[test]
+    fn test_find_first_of() {
+        let fixture = parse_node("<host><p_ncpus>8</p_ncpus><bad>x</bad></host>")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            Some(8),
+            fixture.find_first_of::<i64>(&["ncpus", "p_ncpus"]).unwrap()
+        );
+        assert_eq!(
+            8,
+            fixture
+                .find_first_of1::<i64>(&["ncpus", "p_ncpus"])
+                .unwrap()
+        );
+        assert!(fixture.find_first_of::<i64>(&["bad", "p_ncpus"]).is_err());
+        assert_eq!(
+            None,
+            fixture.find_first_of::<i64>(&["ncpus", "cpus"]).unwrap()
+        );
+
+        let e = fixture
+            .find_first_of1::<i64>(&["ncpus", "cpus"])
+            .unwrap_err();
+        assert!(matches!(&e, UtilError::ValuesNotFound { paths } if paths == &["ncpus", "cpus"]));
+        assert_eq!(
+            "value not found at any of host.ncpus, host.cpus",
+            e.within("host").to_string()
+        );
+    }
+
+    #[test]
+    fn test_find_value_indexed() {
+        let fixture = parse_node(
+            "<reply><result><name>a</name></result><result><name>b</name><flag/></result><result><name>c</name></result></reply>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            "a",
+            fixture.find_value1::<String, _>("result[0].name").unwrap()
+        );
+        assert_eq!(
+            "b",
+            fixture.find_value1::<String, _>("result[1].name").unwrap()
+        );
+        assert_eq!(
+            "c",
+            fixture.find_value1::<String, _>("result[2]/name").unwrap()
+        );
+        assert!(fixture.find_bool("result[1].flag").unwrap());
+        assert!(!fixture.find_bool("result[2].flag").unwrap());
+
+        assert_eq!(
+            None,
+            fixture.find_value0::<String, _>("result[3].name").unwrap()
+        );
+        assert!(fixture.find_value1::<String, _>("result[3].name").is_err());
+        assert!(fixture.find_value0::<String, _>("result[x].name").is_err());
+    }
+
+    #[test]
+    fn test_find_value_dotted_name() {
+        let fixture = parse_node(
+            "<reply><app.version><major>7</major></app.version><app><version>x</version></app></reply>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            7,
+            fixture
+                .find_value1::<i64, _>(r"app\.version.major")
+                .unwrap()
+        );
+        assert_eq!(
+            7,
+            fixture
+                .find_value1::<i64, _>(r"app\.version[0]/major")
+                .unwrap()
+        );
+        assert_eq!(
+            "x",
+            fixture.find_value1::<String, _>("app.version").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_value_predicate() {
+        let fixture = parse_node(
+            r#"<tasks><task name="einstein"><estimated_cpu_time>1</estimated_cpu_time></task><task name="setiathome"><estimated_cpu_time>2</estimated_cpu_time></task><task name="setiathome"><estimated_cpu_time>3</estimated_cpu_time></task><task name='say "hi"'><estimated_cpu_time>4</estimated_cpu_time></task></tasks>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            2,
+            fixture
+                .find_value1::<i64, _>("task[@name=\"setiathome\"].estimated_cpu_time")
+                .unwrap()
+        );
+        assert_eq!(
+            1,
+            fixture
+                .find_value1::<i64, _>("task[@name='einstein'].estimated_cpu_time")
+                .unwrap()
+        );
+        assert_eq!(
+            4,
+            fixture
+                .find_value1::<i64, _>(r#"task[@name="say \"hi\""].estimated_cpu_time"#)
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            fixture
+                .find_value0::<i64, _>("task[@name=\"rosetta\"].estimated_cpu_time")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_value_wildcard() {
+        let fixture = parse_node(
+            "<client><project><name>a</name></project><app><name>b</name></app><app/><host><name>c</name><ip>1</ip></host></client>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            fixture.find_value_all::<String, _>("*.name").unwrap()
+        );
+        assert_eq!(
+            vec!["c".to_string(), "1".to_string()],
+            fixture.find_value_all::<String, _>("host.*").unwrap()
+        );
+        assert_eq!(
+            vec!["b".to_string()],
+            fixture.find_value_all::<String, _>("app/*").unwrap()
+        );
+
+        // The first match in document order wins
+        assert_eq!("a", fixture.find_value1::<String, _>("*.name").unwrap());
+        assert_eq!("1", fixture.find_value1::<String, _>("*.ip").unwrap());
+        assert!(fixture.find_value1::<String, _>("*.missing").is_err());
+    }
+
+    #[test]
+    fn test_find_descendant() {
+        let fixture = parse_node(
+            "<reply><file_info><name>a</name><md5_cksum>1</md5_cksum></file_info><app><version><file><md5_cksum>2</md5_cksum></file></version></app></reply>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            Some(1),
+            fixture.find_descendant_value0::<i64>("md5_cksum").unwrap()
+        );
+        assert_eq!(
+            Some(2),
+            fixture
+                .find("app")
+                .unwrap()
+                .find_descendant_value0::<i64>("md5_cksum")
+                .unwrap()
+        );
+        assert_eq!("file", fixture.find_descendant("file").unwrap().name);
+        assert_eq!("reply", fixture.find_descendant("reply").unwrap().name);
+        assert!(fixture.find_descendant("missing").is_none());
+        assert_eq!(
+            None,
+            fixture.find_descendant_value0::<i64>("missing").unwrap()
+        );
+
+        let result = fixture
+            .find_descendants("md5_cksum")
+            .into_iter()
+            .map(|e| e.text.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["1".to_string(), "2".to_string()], result);
+        assert!(fixture.find_descendants("missing").is_empty());
+    }
+
+    #[test]
+    fn test_children_named() {
+        let fixture = make_tree_element(
+            "reply",
+            (0..10_000)
+                .map(|i| make_text_element(if i % 2 == 0 { "result" } else { "other" }, i))
+                .collect(),
+        );
+
+        let mut n = 0;
+        for (i, result) in fixture.children_named("result").enumerate() {
+            assert!(std::ptr::eq(result, &fixture.children[i * 2]));
+            n += 1;
+        }
+
+        assert_eq!(5_000, n);
+        assert_eq!(0, fixture.children_named("missing").count());
+    }
+
+    #[test]
+    fn test_children_named_mut() {
+        let mut fixture =
+            parse_node("<reply><result>1</result><other>2</other><result>3</result></reply>")
+                .unwrap()
+                .unwrap();
+
+        for result in fixture.children_named_mut("result") {
+            result.text = Some("edited".into());
+        }
+
+        assert_eq!(
+            vec!["edited".to_string(), "edited".to_string()],
+            fixture.find_value_all::<String, _>("result").unwrap()
+        );
+        assert_eq!("2", fixture.find_value1::<String, _>("other").unwrap());
+    }
+
+    #[test]
+    fn test_descendants() {
+        let fixture = parse_node("<a><b><c/><d/></b><e><f><g/></f></e><h/></a>")
+            .unwrap()
+            .unwrap();
+
+        let result = fixture
+            .descendants()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a", "b", "c", "d", "e", "f", "g", "h"], result);
+
+        let result = fixture
+            .descendants_with_depth()
+            .map(|(depth, e)| (depth, e.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (0, "a"),
+                (1, "b"),
+                (2, "c"),
+                (2, "d"),
+                (1, "e"),
+                (2, "f"),
+                (3, "g"),
+                (1, "h")
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn test_find_child_by() {
+        let mut fixture = parse_node(
+            "<state><app><name>a</name><version>1</version></app><app><name>b</name><version>2</version></app><app><name>b</name><version>3</version></app></state>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let is_b = |e: &treexml::Element| {
+            e.name == "app" && e.find_value0::<String, _>("name").unwrap().as_deref() == Some("b")
+        };
+
+        assert_eq!(
+            2,
+            fixture
+                .find_child_by(is_b)
+                .unwrap()
+                .find_value1::<i64, _>("version")
+                .unwrap()
+        );
+        assert_eq!(2, fixture.filter_children_by(is_b).count());
+        assert!(fixture.find_child_by(|e| e.name == "project").is_none());
+
+        fixture
+            .find_child_by_mut(is_b)
+            .unwrap()
+            .children
+            .iter_mut()
+            .find(|c| c.name == "version")
+            .unwrap()
+            .text = Some("20".into());
+
+        assert_eq!(
+            vec![1, 20, 3],
+            fixture.find_value_all::<i64, _>("app.version").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_mut() {
+        let mut fixture = parse_node(
+            "<state><project><app><name>a</name></app></project><project><app><name>b</name><user_friendly_name>B</user_friendly_name></app></project></state>",
+        )
+        .unwrap()
+        .unwrap();
+
+        fixture
+            .find_mut("project.app.user_friendly_name")
+            .unwrap()
+            .text = Some("Edited".into());
+        fixture.find_mut("project[0].app.name").unwrap().text = Some("c".into());
+
+        assert_eq!(
+            "Edited",
+            fixture
+                .find_value1::<String, _>("project[1].app.user_friendly_name")
+                .unwrap()
+        );
+        assert_eq!(
+            "c",
+            fixture
+                .find_value1::<String, _>("project.app.name")
+                .unwrap()
+        );
+
+        assert!(fixture.find_mut0("project.missing").unwrap().is_none());
+        assert!(fixture.find_mut("project.missing").is_err());
+        assert_eq!("state", fixture.find_mut("").unwrap().name);
+    }
+
+    #[test]
+    fn test_set_content() {
+        let mut fixture =
+            parse_node("<project><name>a</name><desc><![CDATA[old]]></desc><url>x</url></project>")
+                .unwrap()
+                .unwrap();
+
+        fixture.set_text("name", "b").unwrap();
+        assert_eq!(Some("b".to_string()), fixture.find("name").unwrap().text);
+
+        fixture.set_text("desc", 5).unwrap();
+        let desc = fixture.find("desc").unwrap();
+        assert_eq!(Some("5".to_string()), desc.text);
+        assert_eq!(None, desc.cdata);
+
+        fixture.set_cdata("name", "<new>").unwrap();
+        let name = fixture.find("name").unwrap();
+        assert_eq!(None, name.text);
+        assert_eq!(Some("<new>".to_string()), name.cdata);
+
+        fixture.clear_content("url").unwrap();
+        assert_eq!(None, fixture.find("url").unwrap().text_content());
+
+        assert!(fixture.set_text("missing", "x").is_err());
+        assert!(fixture.set_cdata("missing", "x").is_err());
+        assert!(fixture.clear_content("missing").is_err());
+        assert!(fixture.find("missing").is_err());
+    }
+
+    #[test]
+    fn test_set_attr() {
+        let mut fixture = parse_node(r#"<request><task name="x"/></request>"#)
+            .unwrap()
+            .unwrap();
+
+        fixture.set_attr("task", "priority", 5).unwrap();
+        fixture.set_attr("", "version", "7.0").unwrap();
+        fixture.set_attr("task", "name", "y").unwrap();
+
+        assert_eq!(
+            Some(5),
+            fixture
+                .find_attr_value0::<i64, _>("task", "priority")
+                .unwrap()
+        );
+        assert_eq!(
+            "7.0",
+            fixture
+                .find_attr_value1::<String, _>("", "version")
+                .unwrap()
+        );
+        assert_eq!(
+            "y",
+            fixture
+                .find_attr_value1::<String, _>("task", "name")
+                .unwrap()
+        );
+
+        assert!(fixture.remove_attr("task", "name").unwrap());
+        assert!(!fixture.remove_attr("task", "name").unwrap());
+        assert_eq!(1, fixture.find("task").unwrap().attributes.len());
+
+        assert!(fixture.set_attr("missing", "a", 1).is_err());
+        assert!(fixture.remove_attr("missing", "a").is_err());
+    }
+
+    #[test]
+    fn test_child_editing() {
+        let mut fixture = parse_node(
+            "<state><projects><project>a</project><other/><project>b</project></projects></state>",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(2, fixture.remove_children("projects", "project").unwrap());
+        assert_eq!(0, fixture.remove_children("projects", "project").unwrap());
+
+        fixture
+            .insert_child("projects", make_text_element("project", "c"))
+            .unwrap();
+        fixture
+            .insert_child_at("projects", 0, make_text_element("project", "a"))
+            .unwrap();
+        fixture
+            .insert_child_at("projects", 3, make_text_element("project", "d"))
+            .unwrap();
+        assert!(fixture
+            .insert_child_at("projects", 5, make_text_element("project", "e"))
+            .is_err());
+
+        let old = fixture
+            .replace_child("projects", 1, make_text_element("project", "b"))
+            .unwrap();
+        assert_eq!("other", old.name);
+        assert!(fixture
+            .replace_child("projects", 4, make_text_element("project", "e"))
+            .is_err());
+
+        assert_eq!(
+            vec!["a", "b", "c", "d"],
+            fixture
+                .find_value_all::<String, _>("projects.project")
+                .unwrap()
+        );
+        assert!(fixture
+            .insert_child("missing", make_text_element("project", "x"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_take_child() {
+        let mut fixture = parse_node(
+            "<reply><result>1</result><other/><result>2</result><result>3</result></reply>",
+        )
+        .unwrap()
+        .unwrap();
+        let text_ptr = fixture.children[0].text.as_ref().unwrap().as_ptr();
+
+        let result = fixture.take_child("result").unwrap();
+        assert_eq!(Some("1".to_string()), result.text);
+        assert_eq!(text_ptr, result.text.as_ref().unwrap().as_ptr());
+        assert_eq!(3, fixture.children.len());
+
+        let text_ptr = fixture.children[1].text.as_ref().unwrap().as_ptr();
+        let result = fixture.take_children("result");
+        assert_eq!(2, result.len());
+        assert_eq!(text_ptr, result[0].text.as_ref().unwrap().as_ptr());
+        assert_eq!(Some("3".to_string()), result[1].text);
+        assert_eq!(1, fixture.children.len());
+
+        assert!(fixture.take_child("result").is_none());
+        assert!(fixture.take_children("result").is_empty());
+    }
+
+    #[test]
+    fn test_sort_children() {
+        let mut fixture = parse_node(
+            "<projects><project><name>b</name></project><project><name>c</name></project><project><name>a</name></project></projects>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = vec!["a", "b", "c"];
+
+        fixture.sort_children_by(|a, b| {
+            let a = a.find_value1::<String, _>("name").unwrap();
+            let b = b.find_value1::<String, _>("name").unwrap();
+            a.cmp(&b)
+        });
+        let result = fixture.find_value_all::<String, _>("project.name").unwrap();
+        assert_eq!(expectation, result);
+
+        fixture.sort_children_by_key(|c| {
+            std::cmp::Reverse(c.find_value1::<String, _>("name").unwrap())
+        });
+        let result = fixture.find_value_all::<String, _>("project.name").unwrap();
+        assert_eq!(vec!["c", "b", "a"], result);
+    }
+
+    #[test]
+    fn test_sort_children_recursive() {
+        let mut fixture = parse_node("<a><c><f/><e/></c><b><d/></b></a>")
+            .unwrap()
+            .unwrap();
+        let expectation = parse_node("<a><b><d/></b><c><e/><f/></c></a>")
+            .unwrap()
+            .unwrap();
+
+        fixture.sort_children_by_key_recursive(|c| c.name.clone());
+
+        assert_eq!(expectation, fixture);
+    }
+
+    #[test]
+    fn test_retain_children() {
+        let mut fixture = parse_node(
+            r#"<tasks><task id="1"><step id="a"/><step/></task><task/><task id="2"><step/></task></tasks>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut shallow = fixture.clone();
+        let result = shallow.retain_children(|c| c.attributes.contains_key("id"));
+        assert_eq!(1, result);
+        assert_eq!(2, shallow.children.len());
+        assert_eq!(2, shallow.children[0].children.len());
+
+        let expectation =
+            parse_node(r#"<tasks><task id="1"><step id="a"/></task><task id="2"/></tasks>"#)
+                .unwrap()
+                .unwrap();
+        let result = fixture.retain_children_recursive(|c| c.attributes.contains_key("id"));
+        assert_eq!(3, result);
+        assert_eq!(expectation, fixture);
+    }
+
+    #[test]
+    fn test_map_text() {
+        let mut fixture = parse_node(
+            "<account><authenticator>secret1</authenticator><host>Example.COM</host><project><authenticator><![CDATA[secret2]]></authenticator><name>p</name></project></account>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = parse_node(
+            "<account><authenticator>***</authenticator><host>example.com</host><project><authenticator><![CDATA[***]]></authenticator><name>p</name></project></account>",
+        )
+        .unwrap()
+        .unwrap();
 
-pub trait ElementExt {
-    fn find_value0<T, PATH>(&self, path: PATH) -> anyhow::Result<Option<T>>
-    where
-        PATH: Into<String>,
-        T: std::str::FromStr;
+        fixture.map_text(|name, value| match name {
+            "authenticator" => Some("***".into()),
+            "host" => Some(value.to_lowercase()),
+            _ => None,
+        });
 
-    fn find_value1<T, PATH>(&self, path: PATH) -> anyhow::Result<T>
-    where
-        PATH: Into<String>,
-        T: std::str::FromStr;
+        assert_eq!(expectation, fixture);
+    }
 
-    fn find_bool<PATH>(&self, path: PATH) -> anyhow::Result<bool>
-    where
-        PATH: Into<String>;
+    #[test]
+    fn test_strip_whitespace_text() {
+        let fixture =
+            "<project>\n  <active>\n    true\n  </active>\n  <count> 42\n</count>\n</project>";
 
-    fn unmarshal_into<T>(&self, out: &mut T) -> anyhow::Result<bool>
-    where
-        T: std::str::FromStr,
-        T::Err: std::fmt::Display;
-    fn unmarshal_bool_into(&self, out: &mut bool) -> anyhow::Result<bool>;
-}
+        let raw = parse_node(fixture).unwrap().unwrap();
+        assert!(raw.find_bool("active").unwrap());
+        assert!(raw.find_value0::<i64, _>("count").is_err());
 
-impl ElementExt for treexml::Element {
-    fn find_value0<T, PATH>(&self, path: PATH) -> anyhow::Result<Option<T>>
-    where
-        PATH: Into<String>,
-        T: std::str::FromStr,
-    {
-        let path = path.into();
-        self.find_value(&path).or_else(|e| match e {
-            TreexmlError::ElementNotFound { .. } => Ok(None),
-            _ => Err(e.into()),
-        })
+        let result = parse_node_trimmed(fixture).unwrap().unwrap();
+        assert!(result.find_bool("active").unwrap());
+        assert_eq!(Some(42), result.find_value0::<i64, _>("count").unwrap());
     }
 
-    fn find_value1<T, PATH>(&self, path: PATH) -> anyhow::Result<T>
-    where
-        PATH: Into<String>,
-        T: std::str::FromStr,
-    {
-        let path = path.into();
-        self.find_value0(path.clone())
-            .and_then(|v| v.ok_or_else(|| format_err!("Value not found at path: {}", &path)))
+    #[test]
+    fn test_strip_whitespace_text_cloned() {
+        let mut fixture = make_tree_element(
+            "project",
+            vec![
+                make_text_element("name", " a b "),
+                make_cdata_element("note", "\n  "),
+            ],
+        );
+        fixture.text = Some("\n  ".into());
+        let expectation = make_tree_element(
+            "project",
+            vec![
+                make_text_element("name", "a b"),
+                treexml::Element::new("note"),
+            ],
+        );
+
+        let result = strip_whitespace_text_cloned(&fixture);
+
+        assert_eq!(expectation, result);
+        assert_eq!(Some("\n  ".to_string()), fixture.text);
     }
 
-    fn find_bool<PATH>(&self, path: PATH) -> anyhow::Result<bool>
-    where
-        PATH: Into<String>,
-    {
-        let path = path.into();
-        match self.find(&path) {
-            Ok(ref e) => match e.text {
-                None => Ok(true),
-                Some(ref text) => match text.as_str() {
-                    "true" => Ok(true),
-                    "false" => Ok(false),
-                    "1" => Ok(true),
-                    "0" => Ok(false),
-                    other => Err(format_err!("Invalid boolean value: {}", &other)),
-                },
-            },
-            Err(e) => match e {
-                TreexmlError::ElementNotFound { .. } => Ok(false),
-                _ => Err(e.into()),
-            },
-        }
+    fn fixture_path(name: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
     }
 
-    fn unmarshal_into<T>(&self, out: &mut T) -> anyhow::Result<bool>
-    where
-        T: std::str::FromStr,
-        T::Err: std::fmt::Display,
-    {
-        match self.text {
-            None => Ok(false),
-            Some(ref text) => {
-                std::mem::swap(
-                    out,
-                    &mut match T::from_str(text) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            return Err(TreexmlError::ValueFromStr { t: e.to_string() }.into());
-                        }
-                    },
-                );
-                Ok(true)
+    #[test]
+    fn test_parse_node_from_reader() {
+        let fixture = std::io::Cursor::new(b"<reply><status>ok</status></reply>".to_vec());
+        let expectation = Some("ok".to_string());
+
+        let result = parse_node_from_reader(fixture)
+            .unwrap()
+            .unwrap()
+            .find_value0::<String, _>("status")
+            .unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_parse_node_from_reader_file() {
+        let fixture = std::fs::File::open(fixture_path("sched_reply.xml")).unwrap();
+        let expectation = vec!["wu_1_0", "wu_2_0"];
+
+        let result = parse_node_from_reader(std::io::BufReader::new(fixture))
+            .unwrap()
+            .unwrap()
+            .find_value_all::<String, _>("result.name")
+            .unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_parse_node_error_position() {
+        let fixture = "<config>\n  <a>1</a>\n  <b>\n    <c>2</c>\n</config>\n";
+
+        let e = parse_node(fixture).unwrap_err();
+        assert_eq!(Some(5), e.line());
+        assert!(e.to_string().contains("line 5"), "{}", e);
+
+        let e = parse_node_from_reader(fixture.as_bytes()).unwrap_err();
+        assert_eq!(Some(5), e.line());
+
+        let e = parse_node("<config>\n  <a>1 &bogus; 2</a>\n</config>").unwrap_err();
+        assert_eq!((Some(2), Some(8)), (e.line(), e.column()));
+        assert!(e.to_string().contains("line 2, column 8"), "{}", e);
+        assert!(std::error::Error::source(&e).is_some());
+    }
+
+    #[test]
+    fn test_parse_node_from_reader_error() {
+        struct Failing(bool);
+
+        impl std::io::Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if std::mem::replace(&mut self.0, true) {
+                    return Err(std::io::Error::other("connection reset"));
+                }
+                let data = b"<reply><sta";
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
             }
         }
+
+        let e = parse_node_from_reader(Failing(false)).unwrap_err();
+        assert_eq!(None, e.line());
     }
 
-    fn unmarshal_bool_into(&self, out: &mut bool) -> anyhow::Result<bool> {
-        match self.text {
-            None => {
-                std::mem::swap(out, &mut true);
-                Ok(true)
+    #[cfg(feature = "quick-xml")]
+    #[test]
+    fn test_parse_node_from_reader_replay_limit() {
+        let mut fixture = String::from("<reply>");
+        while fixture.len() <= location::REPLAY_LIMIT {
+            fixture.push_str("<status>ok</status>");
+        }
+
+        let e = parse_node_from_reader(format!("{}<a>", fixture).as_bytes()).unwrap_err();
+        assert!(matches!(e, UtilError::Malformed { .. }), "{}", e);
+
+        let e = parse_node(&format!("{}<a>", fixture)).unwrap_err();
+        assert_eq!(Some(1), e.line());
+
+        let result = parse_node_from_reader(format!("{}</reply>", fixture).as_bytes()).unwrap();
+        assert!(result.is_some());
+    }
+
+    #[test]
+    fn test_parse_node_from_file() {
+        let expectation = Some(707);
+
+        let result = parse_node_from_file(fixture_path("sched_reply.xml"))
+            .unwrap()
+            .unwrap()
+            .find_value0::<i64, _>("scheduler_version")
+            .unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_parse_node_from_file_bom() {
+        let expectation = Some("https://example.com/".to_string());
+
+        let result = parse_node_from_file(fixture_path("bom.xml"))
+            .unwrap()
+            .unwrap()
+            .find_value0::<String, _>("master_url")
+            .unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_parse_node_from_file_errors() {
+        let path = fixture_path("missing.xml");
+        match parse_node_from_file(&path).unwrap_err() {
+            UtilError::Io { path: p, source } => {
+                assert_eq!(path.display().to_string(), p);
+                assert_eq!(std::io::ErrorKind::NotFound, source.kind());
             }
-            Some(ref text) => {
-                std::mem::swap(
-                    out,
-                    &mut match bool::from_str(text) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            return Err(TreexmlError::ValueFromStr { t: e.to_string() }.into());
-                        }
-                    },
-                );
-                Ok(true)
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let path = fixture_path("malformed.xml");
+        match parse_node_from_file(&path).unwrap_err() {
+            UtilError::Parse { path: p, source } => {
+                assert_eq!(path.display().to_string(), p);
+                assert!(matches!(*source, UtilError::Syntax { line: 4, .. }));
             }
+            e => panic!("unexpected error: {}", e),
         }
     }
-}
 
-pub trait Unmarshaller {
-    fn unmarshal_from(&mut self, node: &treexml::Element) -> anyhow::Result<bool>;
-}
+    #[test]
+    fn test_parse_node_bytes_latin1() {
+        let fixture = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><host><domain_name>caf\xe9</domain_name></host>";
+        let expectation = Some("caf\u{e9}".to_string());
 
-impl Unmarshaller for bool {
-    fn unmarshal_from(&mut self, node: &treexml::Element) -> anyhow::Result<bool> {
-        node.unmarshal_bool_into(self)
+        let result = parse_node_bytes(fixture)
+            .unwrap()
+            .unwrap()
+            .find_value0::<String, _>("domain_name")
+            .unwrap();
+
+        assert_eq!(expectation, result);
     }
-}
 
-impl Unmarshaller for i64 {
-    fn unmarshal_from(&mut self, node: &treexml::Element) -> anyhow::Result<bool> {
-        node.unmarshal_into(self)
+    #[test]
+    fn test_parse_node_bytes_utf16() {
+        let mut fixture = vec![0xFF, 0xFE];
+        for unit in "<?xml version=\"1.0\" encoding=\"UTF-16\"?><host><domain_name>caf\u{e9}</domain_name></host>".encode_utf16() {
+            fixture.extend_from_slice(&unit.to_le_bytes());
+        }
+        let expectation = Some("caf\u{e9}".to_string());
+
+        let result = parse_node_bytes(&fixture)
+            .unwrap()
+            .unwrap()
+            .find_value0::<String, _>("domain_name")
+            .unwrap();
+
+        assert_eq!(expectation, result);
     }
-}
 
-impl Unmarshaller for f64 {
-    fn unmarshal_from(&mut self, node: &treexml::Element) -> anyhow::Result<bool> {
-        node.unmarshal_into(self)
+    #[test]
+    fn test_parse_node_bytes_unknown_encoding() {
+        let fixture = b"<?xml version=\"1.0\" encoding=\"X-BOGUS-7\"?><host/>";
+
+        let result = parse_node_bytes(fixture).unwrap_err();
+
+        assert!(
+            matches!(&result, UtilError::UnsupportedEncoding { encoding } if encoding == "x-bogus-7"),
+            "{}",
+            result
+        );
     }
-}
 
-impl Unmarshaller for String {
-    fn unmarshal_from(&mut self, node: &treexml::Element) -> anyhow::Result<bool> {
-        node.unmarshal_into(self)
+    #[test]
+    fn test_parse_fragment() {
+        let expectation = vec![
+            treexml::Element::new("success"),
+            make_tree_element("reply", vec![make_text_element("status", "ok")]),
+        ];
+
+        let result = parse_fragment(
+            "<?xml version=\"1.0\"?>\n<success/>\n  <reply><status>ok</status></reply>\n",
+        )
+        .unwrap();
+
+        assert_eq!(expectation, result);
     }
-}
 
-/// Creates an XML element that contains child elements
-pub fn make_tree_element(name: &str, v: Vec<treexml::Element>) -> treexml::Element {
-    treexml::Element {
-        name: name.into(),
-        children: v,
-        ..Default::default()
+    #[test]
+    fn test_parse_fragment_single_and_empty() {
+        assert_eq!(1, parse_fragment("<reply/>").unwrap().len());
+        assert!(parse_fragment("").unwrap().is_empty());
+        assert!(parse_fragment("  \n").unwrap().is_empty());
     }
-}
 
-/// Creates an XML element with text contents
-pub fn make_text_element<T>(name: &str, v: T) -> treexml::Element
-where
-    T: std::fmt::Display,
-{
-    treexml::Element {
-        name: name.into(),
-        text: Some(v.to_string()),
-        ..Default::default()
+    #[test]
+    fn test_parse_fragment_errors() {
+        assert!(parse_fragment("<a/>stray<b/>").is_err());
+        assert!(parse_fragment("<a><b></a>").is_err());
+        assert!(parse_fragment("<a/><b>").is_err());
     }
-}
 
-/// Creates an XML element with cdata contents
-pub fn make_cdata_element<T>(name: &str, v: T) -> treexml::Element
-where
-    T: std::fmt::Display,
-{
-    treexml::Element {
-        name: name.into(),
-        cdata: Some(v.to_string()),
-        ..Default::default()
+    #[test]
+    fn test_parse_node_lenient() {
+        let fixture = r#"<project url="https://example.com/?a=1&b=2" name="x" name="y"><note>R&D <3</note></project>"#;
+        assert!(parse_node(fixture).is_err());
+
+        let (result, fixes) = parse_node_lenient(fixture).unwrap();
+        let result = result.unwrap();
+
+        assert_eq!(
+            Some("https://example.com/?a=1&b=2"),
+            result.attributes.get("url").map(String::as_str)
+        );
+        assert_eq!(Some("x"), result.attributes.get("name").map(String::as_str));
+        assert_eq!(
+            Some("R&D <3".to_string()),
+            result.find_value0::<String, _>("note").unwrap()
+        );
+        assert_eq!(4, fixes.len());
+        assert!(fixes.contains(&LenientFix::DroppedDuplicateAttribute {
+            offset: 53,
+            element: "project".into(),
+            name: "name".into(),
+        }));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
     #[test]
-    fn test_deserialize() {
-        let fixture = parse_node("<data>5</data>").unwrap().unwrap();
-        let expectation = 5;
+    fn test_parse_node_lenient_unrecoverable() {
+        assert!(parse_node_lenient("<project><name>x</project>").is_err());
+        assert!(parse_node_lenient("<project").is_err());
+    }
 
-        let mut result = i64::default();
-        result.unmarshal_from(&fixture).unwrap();
+    fn limit_error(s: &str, limits: ParseLimits) -> LimitExceeded {
+        match parse_node_with_limits(s, &limits).unwrap_err() {
+            UtilError::LimitExceeded(e) => e,
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_parse_limits_pass() {
+        let limits = ParseLimits {
+            max_depth: 3,
+            max_children: 2,
+            max_text_len: 6,
+            max_total_nodes: 6,
+        };
+
+        let result = parse_node_with_limits(
+            "<?xml version=\"1.0\"?><!-- x --><reply><result a=\"x>y\"><name>wu_1_0</name></result><result><name><![CDATA[wu_2]]></name></result></reply>",
+            &limits,
+        )
+        .unwrap();
+
+        assert!(result.is_some());
+    }
+
+    #[test]
+    fn test_parse_limits_exceeded() {
+        let result = limit_error(
+            "<a><b><c><d/></c></b></a>",
+            ParseLimits {
+                max_depth: 3,
+                ..Default::default()
+            },
+        );
+        assert_eq!(Limit::Depth, result.limit);
+        assert_eq!("a.b.c", result.path);
+        assert_eq!(9, result.offset);
+
+        let result = limit_error(
+            "<a><b/><b/><b/></a>",
+            ParseLimits {
+                max_children: 2,
+                ..Default::default()
+            },
+        );
+        assert_eq!(Limit::Children, result.limit);
+        assert_eq!("a", result.path);
+
+        let result = limit_error(
+            "<a><b>abc<![CDATA[def]]></b></a>",
+            ParseLimits {
+                max_text_len: 5,
+                ..Default::default()
+            },
+        );
+        assert_eq!(Limit::TextLength, result.limit);
+        assert_eq!("a.b", result.path);
 
+        let result = limit_error(
+            "<a><b><c/></b><b/></a>",
+            ParseLimits {
+                max_total_nodes: 3,
+                ..Default::default()
+            },
+        );
+        assert_eq!(Limit::TotalNodes, result.limit);
+        assert_eq!(14, result.offset);
+    }
+
+    #[test]
+    fn test_try_make_cdata_element() {
+        let expectation = make_cdata_element("note", "<b>ok</b>");
+
+        let result = try_make_cdata_element("note", "<b>ok</b>").unwrap();
         assert_eq!(expectation, result);
+        assert_eq!(
+            result,
+            parse_node(&serialize_element(&result)).unwrap().unwrap()
+        );
+
+        assert!(try_make_cdata_element("note", "a]]>b").is_err());
+        assert!(try_make_cdata_element("note", "a\u{0}b").is_err());
     }
 
     #[test]
-    fn test_deserialize_bool() {
-        let fixture = parse_node("<do_want/>").unwrap().unwrap();
-        let expectation = true;
+    fn test_make_element_attrs() {
+        let fixture = make_tree_element_attrs(
+            "file_info",
+            &[("name", &"x"), ("nbytes", &123)],
+            vec![
+                make_text_element_attrs("url", &[("mirror", &true)], "https://example.com/x"),
+                make_cdata_element_attrs("signature", &[("kind", &"md5")], "abc"),
+            ],
+        );
 
-        let mut result = bool::default();
-        result.unmarshal_from(&fixture).unwrap();
+        let result = parse_node(&serialize_element(&fixture)).unwrap().unwrap();
+
+        assert_eq!(fixture, result);
+        assert_eq!(
+            Some("x".to_string()),
+            result.find_attr_value0("", "name").unwrap()
+        );
+        assert_eq!(Some(123), result.find_attr_value0("", "nbytes").unwrap());
+        assert_eq!(
+            Some(true),
+            result.find_attr_value0("url", "mirror").unwrap()
+        );
+        assert_eq!(
+            Some("md5".to_string()),
+            result.find_attr_value0("signature", "kind").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_make_attr_element() {
+        let expectation = parse_node(r#"<file_ref file_name="x" main_program="1"/>"#)
+            .unwrap()
+            .unwrap();
 
+        let result = make_attr_element("file_ref", vec![("file_name", "x"), ("main_program", "1")]);
         assert_eq!(expectation, result);
+
+        let result = make_attr_element(
+            "file_ref",
+            vec![("file_name".to_string(), "x".to_string())]
+                .into_iter()
+                .chain(Some(("main_program".to_string(), 1.to_string()))),
+        );
+        assert_eq!(expectation, result);
+
+        let mut fixture = HashMap::new();
+        fixture.insert("nbytes", 123u64);
+        fixture.insert("max_nbytes", 0u64);
+        let result = make_attr_element("file_info", fixture);
+        assert_eq!(
+            Some("123"),
+            result.attributes.get("nbytes").map(String::as_str)
+        );
+        assert_eq!(
+            Some("0"),
+            result.attributes.get("max_nbytes").map(String::as_str)
+        );
+
+        let result = make_attr_element("x", Some(("v", true)));
+        assert_eq!(
+            make_text_element("x", true).text,
+            result.attributes.get("v").cloned()
+        );
     }
 
     #[test]
-    fn test_find_value() {
-        let root = treexml::Element {
-            name: "root".into(),
-            children: vec![treexml::Element {
-                name: "key".into(),
-                text: Some("value".into()),
-                ..Default::default()
-            }],
-            ..Default::default()
-        };
+    fn test_element_macro() {
+        let name_var = "uppercase";
+        let extra = vec![make_text_element("result_name", "r1")];
+        let expectation = make_tree_element(
+            "get_results",
+            vec![
+                make_text_element("active_only", 1),
+                make_tree_element(
+                    "match",
+                    vec![
+                        make_text_element("name", name_var),
+                        make_tree_element_attrs(
+                            "file_ref",
+                            &[("file_name", &"x"), ("main_program", &1)],
+                            vec![],
+                        ),
+                        make_text_element_attrs("nbytes", &[("unit", &"B")], 10 * 2),
+                    ],
+                ),
+                make_text_element("result_name", "r1"),
+                make_tree_element("empty", vec![]),
+            ],
+        );
 
-        let expectation = "value".to_string();
+        let result = element!("get_results" {
+            "active_only": 1,
+            "match" {
+                "name": name_var,
+                "file_ref" ["file_name" = "x", "main_program" = 1] {},
+                "nbytes" ["unit" = "B"]: 10 * 2,
+            },
+            ..extra,
+            "empty" {},
+        });
 
-        let result = root.find_value1::<String, _>("key").unwrap();
+        assert_eq!(expectation, result);
+        assert_eq!(make_tree_element("a", vec![]), element!("a"));
+    }
+
+    #[test]
+    fn test_make_list_element() {
+        let expectation = parse_node(
+            "<platforms><platform>x86_64</platform><platform>arm64</platform></platforms>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = make_list_element("platforms", "platform", ["x86_64", "arm64"].iter());
+        assert_eq!(expectation, result);
+
+        let result = make_list_element(
+            "platforms",
+            "platform",
+            vec!["x86_64".to_string(), "arm64".into()],
+        );
+        assert_eq!(expectation, result);
+
+        let result = make_element_list("id", 1..=3);
+        assert_eq!(
+            vec![
+                make_text_element("id", 1),
+                make_text_element("id", 2),
+                make_text_element("id", 3)
+            ],
+            result
+        );
+
+        let result = make_list_element("platforms", "platform", Vec::<u8>::new());
+        assert_eq!(make_tree_element("platforms", vec![]), result);
+        assert!(make_element_list("id", std::iter::empty::<u8>()).is_empty());
+    }
+
+    #[test]
+    fn test_make_map_element() {
+        let fixture = vec![("name", 1), ("x-y.z", 2)];
+        let expectation = parse_node("<m><name>1</name><x-y.z>2</x-y.z></m>")
+            .unwrap()
+            .unwrap();
+
+        let result = make_map_element("m", fixture).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_make_map_element_invalid_key() {
+        for key in &["bad key", "1st", ""] {
+            let result = make_map_element("m", vec![("ok", "1"), (*key, "2")]);
+
+            assert!(matches!(result, Err(UtilError::InvalidName { name }) if name == *key));
+        }
+    }
+
+    #[test]
+    fn test_make_map_element_roundtrip() {
+        let mut fixture = HashMap::new();
+        fixture.insert("platform".to_string(), "x86_64".to_string());
+        fixture.insert("cpus".to_string(), "4".to_string());
+        fixture.insert("empty".to_string(), String::new());
+
+        let e = make_map_element("host", &fixture).unwrap();
+        let e = parse_node(&serialize_element(&e)).unwrap().unwrap();
+        let mut result = HashMap::<String, String>::new();
+        result.unmarshal_from(&e).unwrap();
+
+        assert_eq!(fixture, result);
+    }
 
+    #[test]
+    fn test_make_optional_elements() {
+        assert_eq!(
+            Some(make_text_element("credit", 1.5)),
+            make_optional_text_element("credit", &Some(1.5))
+        );
+        assert_eq!(None, make_optional_text_element::<f64>("credit", &None));
+        assert_eq!(
+            Some(make_tree_element("suspended", vec![])),
+            make_optional_bool_element("suspended", true)
+        );
+        assert_eq!(None, make_optional_bool_element("suspended", false));
+
+        let mut children = Vec::new();
+        push_optional(&mut children, "name", &Some("x"));
+        push_optional::<&str>(&mut children, "url", &None);
+        children.extend(make_optional_bool_element("suspended", true));
+        children.extend(make_optional_bool_element("aborted", false));
+        let result = make_tree_element("project", children);
+
+        let expectation = parse_node("<project><name>x</name><suspended/></project>")
+            .unwrap()
+            .unwrap();
         assert_eq!(expectation, result);
+        assert!(result.find_bool("suspended").unwrap());
+        assert!(!result.find_bool("aborted").unwrap());
+    }
+
+    #[test]
+    fn test_make_bool_element() {
+        let fixture = [
+            (BoolStyle::Numeric, true, Some("<suspend>1</suspend>")),
+            (BoolStyle::Numeric, false, Some("<suspend>0</suspend>")),
+            (BoolStyle::Word, true, Some("<suspend>true</suspend>")),
+            (BoolStyle::Word, false, Some("<suspend>false</suspend>")),
+            (BoolStyle::PresenceOnly, true, Some("<suspend/>")),
+            (BoolStyle::PresenceOnly, false, None),
+        ];
+
+        for (style, v, expectation) in fixture.iter().cloned() {
+            let result = make_bool_element("suspend", v, style);
+            assert_eq!(
+                expectation,
+                result.as_ref().map(serialize_element).as_deref(),
+                "{:?}",
+                style
+            );
+
+            let root = make_tree_element("prefs", result.into_iter().collect());
+            assert_eq!(v, root.find_bool("suspend").unwrap(), "{:?}", style);
+        }
+    }
+
+    #[test]
+    fn test_make_ns_element() {
+        const SOAP: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+        const BOINC: &str = "https://boinc.berkeley.edu/rpc";
+
+        let body = make_ns_element(
+            Some("soap"),
+            "Body",
+            None,
+            vec![make_ns_element(
+                None,
+                "get_state",
+                Some(BOINC),
+                vec![make_text_element("verbose", 1)],
+            )],
+        );
+        let mut fixture = make_ns_element(Some("soap"), "Envelope", Some(SOAP), vec![body]);
+        declare_namespace(&mut fixture, Some("b"), BOINC);
+
+        let result = serialize_element(&fixture);
+        assert_eq!(
+            format!(
+                r#"<soap:Envelope xmlns:b="{}" xmlns:soap="{}"><soap:Body><get_state xmlns="{}"><verbose>1</verbose></get_state></soap:Body></soap:Envelope>"#,
+                BOINC, SOAP, BOINC
+            ),
+            result
+        );
+
+        let result = parse_node(&result).unwrap().unwrap();
+        assert_eq!(Some("soap"), result.prefix.as_deref());
+        assert_eq!("Envelope", result.name);
+        let body = &result.children[0];
+        assert_eq!(
+            (Some("soap"), "Body"),
+            (body.prefix.as_deref(), body.name.as_str())
+        );
+        assert!(body.attributes.is_empty());
+        assert_eq!(
+            (None, "get_state"),
+            (
+                body.children[0].prefix.as_deref(),
+                body.children[0].name.as_str()
+            )
+        );
+        assert_eq!(1, body.children[0].find_value1::<u8, _>("verbose").unwrap());
+    }
+
+    #[test]
+    fn test_find_local_prefixed() {
+        let fixture = make_tree_element(
+            "soap:Envelope",
+            vec![make_tree_element(
+                "soap:Body",
+                vec![make_tree_element(
+                    "m:Response",
+                    vec![make_text_element("m:Status", "ok")],
+                )],
+            )],
+        );
+
+        assert!(fixture
+            .find_value1::<String, _>("Body.Response.Status")
+            .is_err());
+        assert_eq!(
+            "ok",
+            fixture
+                .find_value_local1::<String, _>("Body.Response.Status", None)
+                .unwrap()
+        );
+
+        let fixture = parse_node_with_namespaces(
+            r#"<soap:Envelope xmlns:soap="urn:soap" xmlns:m="urn:m"><soap:Body><m:Response><m:Status>ok</m:Status></m:Response></soap:Body></soap:Envelope>"#,
+        )
+        .unwrap()
+        .unwrap();
+        let result = fixture
+            .find_value_local1::<String, _>("Body.Response.Status", Some("urn:m"))
+            .unwrap();
+        assert_eq!("ok", result);
+        assert_eq!(
+            None,
+            fixture
+                .find_value_local0::<String, _>("Body.Response.Status", Some("urn:soap"))
+                .unwrap()
+        );
+        assert_eq!(
+            Some("Body"),
+            fixture
+                .find_local("Body", Some("urn:soap"))
+                .unwrap()
+                .map(|e| e.name.as_str())
+        );
+    }
+
+    #[test]
+    fn test_find_local_default_namespace() {
+        let fixture =
+            parse_node_with_namespaces(r#"<Envelope xmlns="urn:a"><Status>ok</Status><x xmlns=""><Status>bare</Status></x></Envelope>"#)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(
+            Some("ok".to_string()),
+            fixture.find_value_local0("Status", Some("urn:a")).unwrap()
+        );
+        assert_eq!(
+            None,
+            fixture
+                .find_value_local0::<String, _>("Status", Some("urn:b"))
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            fixture
+                .find_value_local0::<String, _>("x.Status", Some("urn:a"))
+                .unwrap()
+        );
+        assert_eq!(
+            "bare",
+            fixture
+                .find_value_local1::<String, _>("x.Status", None)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_local_namespace_collision() {
+        let fixture = parse_node_with_namespaces(
+            r#"<r xmlns:a="urn:a" xmlns:b="urn:b"><a:Status>1</a:Status><b:Status>2</b:Status></r>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            1,
+            fixture.find_value_local1::<u8, _>("Status", None).unwrap()
+        );
+        assert_eq!(
+            1,
+            fixture
+                .find_value_local1::<u8, _>("Status", Some("urn:a"))
+                .unwrap()
+        );
+        assert_eq!(
+            2,
+            fixture
+                .find_value_local1::<u8, _>("Status", Some("urn:b"))
+                .unwrap()
+        );
+        assert!(fixture
+            .find_value_local1::<u8, _>("Status", Some("urn:c"))
+            .is_err());
     }
 }
@@ -0,0 +1,200 @@
+//! Resource limits for parsing untrusted input.
+
+use std::fmt;
+
+/// Bounds enforced by `parse_node_with_limits` before any tree is built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum nesting depth; the root element has depth 1.
+    pub max_depth: usize,
+    /// Maximum number of direct children of any element.
+    pub max_children: usize,
+    /// Maximum length in bytes of the text and cdata of any element, before entity expansion.
+    pub max_text_len: usize,
+    /// Maximum number of elements in the document.
+    pub max_total_nodes: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 256,
+            max_children: 100_000,
+            max_text_len: 16 * 1024 * 1024,
+            max_total_nodes: 1_000_000,
+        }
+    }
+}
+
+/// The limit that a document exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    Children,
+    TextLength,
+    TotalNodes,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "max_depth",
+            Limit::Children => "max_children",
+            Limit::TextLength => "max_text_len",
+            Limit::TotalNodes => "max_total_nodes",
+        })
+    }
+}
+
+/// Error returned when a document exceeds one of its `ParseLimits`.
+///
+/// `path` lists the names of the enclosing elements, separated by `.`, and `offset` is the byte
+/// position at which the limit tripped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub max: usize,
+    pub path: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse limit {} ({}) exceeded at {} (byte {})",
+            self.limit, self.max, self.path, self.offset
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+struct Open<'a> {
+    name: &'a str,
+    children: usize,
+    text_len: usize,
+}
+
+/// Scans the markup of `s` and checks it against `limits`.
+///
+/// Malformed markup ends the scan early and is left for the parser to report.
+pub(crate) fn check(s: &str, limits: &ParseLimits) -> Result<(), LimitExceeded> {
+    let mut stack = Vec::<Open>::new();
+    let mut total = 0usize;
+    let mut i = 0;
+
+    let exceeded = |stack: &[Open], limit, max, offset| LimitExceeded {
+        limit,
+        max,
+        path: stack.iter().map(|e| e.name).collect::<Vec<_>>().join("."),
+        offset,
+    };
+
+    while i < s.len() {
+        let rest = &s[i..];
+
+        let skip = |end: &str| rest.find(end).map(|n| n + end.len());
+        let consumed = if rest.starts_with("<!--") {
+            skip("-->")
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let len = cdata.find("]]>");
+            if let (Some(len), Some(open)) = (len, stack.last_mut()) {
+                open.text_len += len;
+                if open.text_len > limits.max_text_len {
+                    return Err(exceeded(&stack, Limit::TextLength, limits.max_text_len, i));
+                }
+            }
+            len.map(|n| n + "<![CDATA[]]>".len())
+        } else if rest.starts_with("<?") {
+            skip("?>")
+        } else if rest.starts_with("</") {
+            stack.pop();
+            skip(">")
+        } else if rest.starts_with("<!") {
+            skip(">")
+        } else if let Some(tag) = rest.strip_prefix('<') {
+            let len = match tag_end(tag) {
+                Some(n) => n,
+                None => return Ok(()),
+            };
+            let name_len = tag
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .unwrap_or(len);
+            let name = &tag[..name_len];
+            let empty = tag[..len].ends_with('/');
+
+            total += 1;
+            if total > limits.max_total_nodes {
+                return Err(exceeded(
+                    &stack,
+                    Limit::TotalNodes,
+                    limits.max_total_nodes,
+                    i,
+                ));
+            }
+            if let Some(parent) = stack.last_mut() {
+                parent.children += 1;
+                if parent.children > limits.max_children {
+                    return Err(exceeded(&stack, Limit::Children, limits.max_children, i));
+                }
+            }
+            if stack.len() + 1 > limits.max_depth {
+                return Err(exceeded(&stack, Limit::Depth, limits.max_depth, i));
+            }
+            if !empty {
+                stack.push(Open {
+                    name,
+                    children: 0,
+                    text_len: 0,
+                });
+            }
+
+            Some(len + 2)
+        } else {
+            let len = rest.find('<').unwrap_or(rest.len());
+            if let Some(open) = stack.last_mut() {
+                open.text_len += len;
+                if open.text_len > limits.max_text_len {
+                    return Err(exceeded(&stack, Limit::TextLength, limits.max_text_len, i));
+                }
+            }
+            Some(len)
+        };
+
+        match consumed {
+            Some(n) => i += n,
+            None => return Ok(()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the position of the `>` closing the tag that `s` starts inside of, skipping quoted
+/// values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_end() {
+        assert_eq!(Some(10), tag_end(r#"a x="1>2"/>rest"#));
+        assert_eq!(None, tag_end(r#"a x=">"#));
+    }
+}
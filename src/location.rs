@@ -0,0 +1,89 @@
+//! Line and column positions for parse errors.
+
+use crate::UtilError;
+use xml::common::Position;
+use xml::reader::ErrorKind;
+
+/// How much of a streamed document is kept so that it can be parsed again with `treexml` after
+/// `quick-xml` rejects it.
+#[cfg(feature = "quick-xml")]
+pub(crate) const REPLAY_LIMIT: usize = 64 * 1024;
+
+/// Passes reads through to `R`, keeping a copy of the first `REPLAY_LIMIT` bytes so that they can
+/// be read again.
+#[cfg(feature = "quick-xml")]
+pub(crate) struct Replay<R> {
+    inner: R,
+    data: Vec<u8>,
+    /// Whether more than `REPLAY_LIMIT` bytes were read, in which case `data` is dropped.
+    overflowed: bool,
+    /// How much of `data` has been read again since `rewind`, if it was called.
+    replayed: Option<usize>,
+}
+
+#[cfg(feature = "quick-xml")]
+impl<R> Replay<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            data: Vec::new(),
+            overflowed: false,
+            replayed: None,
+        }
+    }
+
+    /// Makes the following reads return the bytes read so far again before reading on. Returns
+    /// `false`, changing nothing, if they were too many to keep.
+    pub(crate) fn rewind(&mut self) -> bool {
+        if self.overflowed {
+            return false;
+        }
+        self.replayed = Some(0);
+        true
+    }
+}
+
+#[cfg(feature = "quick-xml")]
+impl<R> std::io::Read for Replay<R>
+where
+    R: std::io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if let Some(pos) = self.replayed {
+            if pos < self.data.len() {
+                let n = buf.len().min(self.data.len() - pos);
+                buf[..n].copy_from_slice(&self.data[pos..pos + n]);
+                self.replayed = Some(pos + n);
+                return Ok(n);
+            }
+            return self.inner.read(buf);
+        }
+
+        let n = self.inner.read(buf)?;
+        if !self.overflowed {
+            if self.data.len() + n > REPLAY_LIMIT {
+                self.overflowed = true;
+                self.data = Vec::new();
+            } else {
+                self.data.extend_from_slice(&buf[..n]);
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Attaches the position `xml-rs` reported for a syntax error to `e`. Read failures and errors
+/// that are not about the syntax are returned without a position.
+pub(crate) fn locate(e: treexml::Error) -> UtilError {
+    match &e {
+        treexml::Error::ParseError(err) if !matches!(err.kind(), ErrorKind::Io(_)) => {
+            let p = err.position();
+            UtilError::Syntax {
+                line: p.row + 1,
+                column: p.column + 1,
+                source: e,
+            }
+        }
+        _ => UtilError::Xml(e),
+    }
+}
@@ -0,0 +1,202 @@
+//! Combining a base element with an overlay.
+
+use std::collections::HashMap;
+
+/// How children of the overlay relate to same-named children of the base.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChildMerge {
+    /// Overlay children replace every base child with the same name.
+    Replace,
+    /// Overlay children are appended after the base's.
+    Append,
+    /// The n-th overlay child named `x` is merged recursively into the n-th base child named `x`.
+    Merge,
+}
+
+/// Controls how `merge_elements` resolves conflicts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergePolicy {
+    pub children: ChildMerge,
+    /// Whether overlay text, cdata and attribute values replace the base's when both are set.
+    pub overlay_wins: bool,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        Self {
+            children: ChildMerge::Merge,
+            overlay_wins: true,
+        }
+    }
+}
+
+/// Merges `overlay` into a copy of `base` according to `policy`.
+///
+/// The result keeps the name and prefix of `base`. Attributes are merged key-wise.
+pub fn merge_elements(
+    base: &treexml::Element,
+    overlay: &treexml::Element,
+    policy: MergePolicy,
+) -> treexml::Element {
+    let mut out = base.clone();
+    merge_into(&mut out, overlay, policy);
+    out
+}
+
+fn merge_into(out: &mut treexml::Element, overlay: &treexml::Element, policy: MergePolicy) {
+    for (k, v) in &overlay.attributes {
+        if policy.overlay_wins || !out.attributes.contains_key(k) {
+            out.attributes.insert(k.clone(), v.clone());
+        }
+    }
+
+    if overlay.text.is_some() && (policy.overlay_wins || out.text.is_none()) {
+        out.text.clone_from(&overlay.text);
+    }
+    if overlay.cdata.is_some() && (policy.overlay_wins || out.cdata.is_none()) {
+        out.cdata.clone_from(&overlay.cdata);
+    }
+
+    match policy.children {
+        ChildMerge::Append => out.children.extend(overlay.children.iter().cloned()),
+        ChildMerge::Replace => {
+            let mut placed = Vec::new();
+            let mut children = Vec::with_capacity(out.children.len());
+            for child in std::mem::take(&mut out.children) {
+                if !overlay.children.iter().any(|c| c.name == child.name) {
+                    children.push(child);
+                } else if !placed.contains(&child.name) {
+                    children.extend(
+                        overlay
+                            .children
+                            .iter()
+                            .filter(|c| c.name == child.name)
+                            .cloned(),
+                    );
+                    placed.push(child.name);
+                }
+            }
+            children.extend(
+                overlay
+                    .children
+                    .iter()
+                    .filter(|c| !placed.contains(&c.name))
+                    .cloned(),
+            );
+            out.children = children;
+        }
+        ChildMerge::Merge => {
+            let mut seen = HashMap::<&str, usize>::new();
+            for child in &overlay.children {
+                let n = seen.entry(&child.name).or_default();
+                let target = out
+                    .children
+                    .iter_mut()
+                    .filter(|c| c.name == child.name)
+                    .nth(*n);
+                *n += 1;
+
+                match target {
+                    Some(target) => merge_into(target, child, policy),
+                    None => out.children.push(child.clone()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    const BASE: &str = r#"<request version="1" mode="sync"><auth><user>guest</user><token>none</token></auth><item>a</item><item>b</item></request>"#;
+    const OVERLAY: &str =
+        r#"<request version="2"><auth><token>secret</token></auth><item>c</item></request>"#;
+
+    fn merge(policy: MergePolicy) -> treexml::Element {
+        let base = parse_node(BASE).unwrap().unwrap();
+        let overlay = parse_node(OVERLAY).unwrap().unwrap();
+
+        merge_elements(&base, &overlay, policy)
+    }
+
+    #[test]
+    fn test_merge_recursive() {
+        let expectation = parse_node(
+            r#"<request version="2" mode="sync"><auth><user>guest</user><token>secret</token></auth><item>c</item><item>b</item></request>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = merge(MergePolicy::default());
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_merge_replace() {
+        let expectation = parse_node(
+            r#"<request version="2" mode="sync"><auth><token>secret</token></auth><item>c</item></request>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = merge(MergePolicy {
+            children: ChildMerge::Replace,
+            overlay_wins: true,
+        });
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_merge_append() {
+        let expectation = parse_node(
+            r#"<request version="2" mode="sync"><auth><user>guest</user><token>none</token></auth><item>a</item><item>b</item><auth><token>secret</token></auth><item>c</item></request>"#,
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = merge(MergePolicy {
+            children: ChildMerge::Append,
+            overlay_wins: true,
+        });
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_merge_base_wins() {
+        let expectation = parse_node(BASE).unwrap().unwrap();
+
+        let result = merge(MergePolicy {
+            children: ChildMerge::Merge,
+            overlay_wins: false,
+        });
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_merge_new_attributes() {
+        let base = parse_node(r#"<a x="1"><b/></a>"#).unwrap().unwrap();
+        let overlay = parse_node(r#"<a y="2"><b z="3"/><c/></a>"#)
+            .unwrap()
+            .unwrap();
+        let expectation = parse_node(r#"<a x="1" y="2"><b z="3"/><c/></a>"#)
+            .unwrap()
+            .unwrap();
+
+        let result = merge_elements(
+            &base,
+            &overlay,
+            MergePolicy {
+                children: ChildMerge::Merge,
+                overlay_wins: false,
+            },
+        );
+
+        assert_eq!(expectation, result);
+    }
+}
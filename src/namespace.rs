@@ -0,0 +1,192 @@
+//! Namespace-aware parsing and lookup by local name.
+
+use crate::path::Segment;
+use crate::UtilError;
+use std::collections::BTreeMap;
+use xml::reader::{EventReader, XmlEvent};
+
+/// Parses a document like `parse_node`, keeping namespace declarations as `xmlns` attributes.
+///
+/// An element gets a declaration for every binding it introduces or changes relative to its parent.
+pub(crate) fn parse<R>(r: R) -> Result<Option<treexml::Element>, UtilError>
+where
+    R: std::io::Read,
+{
+    let mut stack = Vec::<(treexml::Element, BTreeMap<String, String>)>::new();
+    let mut root = None;
+
+    for event in EventReader::new(r) {
+        match event? {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let mut e = treexml::Element {
+                    prefix: name.prefix,
+                    name: name.local_name,
+                    ..Default::default()
+                };
+                for a in attributes {
+                    let key = match a.name.prefix {
+                        Some(prefix) => format!("{}:{}", prefix, a.name.local_name),
+                        None => a.name.local_name,
+                    };
+                    e.attributes.insert(key, a.value);
+                }
+
+                let empty = BTreeMap::new();
+                let parent = stack.last().map_or(&empty, |(_, ns)| ns);
+                for (prefix, uri) in &namespace.0 {
+                    if prefix == "xml" || prefix == "xmlns" {
+                        continue;
+                    }
+                    let inherited = parent.get(prefix).map_or("", String::as_str);
+                    if inherited != uri {
+                        e.attributes.insert(declaration(prefix), uri.clone());
+                    }
+                }
+
+                stack.push((e, namespace.0));
+            }
+            XmlEvent::Characters(s) => {
+                if let Some((e, _)) = stack.last_mut() {
+                    e.text.get_or_insert_with(String::new).push_str(&s);
+                }
+            }
+            XmlEvent::CData(s) => {
+                if let Some((e, _)) = stack.last_mut() {
+                    e.cdata.get_or_insert_with(String::new).push_str(&s);
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                if let Some((e, _)) = stack.pop() {
+                    match stack.last_mut() {
+                        Some((parent, _)) => parent.children.push(e),
+                        None => root = Some(e),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(root)
+}
+
+/// Returns the attribute name declaring `prefix`, with an empty prefix standing for the default
+/// namespace.
+fn declaration(prefix: &str) -> String {
+    if prefix.is_empty() {
+        "xmlns".to_string()
+    } else {
+        format!("xmlns:{}", prefix)
+    }
+}
+
+/// Returns the part of the name of `e` after the last `:`.
+fn local_name(e: &treexml::Element) -> &str {
+    e.name.rsplit(':').next().unwrap_or(&e.name)
+}
+
+/// Returns the namespace URI of the last element of `chain`, looking up declarations from the
+/// element outwards. An empty URI undeclares the default namespace.
+fn namespace_uri<'a>(chain: &[&'a treexml::Element]) -> Option<&'a str> {
+    let e = chain.last()?;
+    let prefix = match &e.prefix {
+        Some(prefix) => prefix.as_str(),
+        None => e.name.rsplit_once(':').map_or("", |(prefix, _)| prefix),
+    };
+    let key = declaration(prefix);
+
+    chain
+        .iter()
+        .rev()
+        .find_map(|e| e.attributes.get(&key))
+        .map(String::as_str)
+        .filter(|uri| !uri.is_empty())
+}
+
+/// Resolves `segments` against local names. With `ns` set, the elements at the end of the path must
+/// also be in that namespace, judged by the declarations on `e` and its descendants.
+pub(crate) fn resolve_local<'a>(
+    e: &'a treexml::Element,
+    segments: &[Segment],
+    ns: Option<&str>,
+) -> Vec<&'a treexml::Element> {
+    let mut chains = vec![vec![e]];
+    for segment in segments {
+        chains = chains
+            .into_iter()
+            .flat_map(|chain| {
+                let parent = chain[chain.len() - 1];
+                segment
+                    .select_indices_by(parent, |c| segment.matches_name(local_name(c)))
+                    .into_iter()
+                    .map(move |i| {
+                        let mut chain = chain.clone();
+                        chain.push(&parent.children[i]);
+                        chain
+                    })
+            })
+            .collect();
+        if chains.is_empty() {
+            break;
+        }
+    }
+
+    chains
+        .iter()
+        .filter(|chain| ns.is_none() || namespace_uri(chain) == ns)
+        .filter_map(|chain| chain.last().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keeps_declarations() {
+        let fixture = r#"<s:a xmlns:s="urn:s" xmlns="urn:d" x="1"><b xmlns:s="urn:s"><c xmlns="urn:e" s:y="2"/></b><d xmlns=""/></s:a>"#;
+
+        let result = parse(fixture.as_bytes()).unwrap().unwrap();
+
+        assert_eq!(Some("s"), result.prefix.as_deref());
+        assert_eq!(
+            vec![("x", "1"), ("xmlns", "urn:d"), ("xmlns:s", "urn:s")],
+            sorted(&result.attributes)
+        );
+        let b = &result.children[0];
+        assert!(b.attributes.is_empty());
+        assert_eq!(
+            vec![("s:y", "2"), ("xmlns", "urn:e")],
+            sorted(&b.children[0].attributes)
+        );
+        assert_eq!(vec![("xmlns", "")], sorted(&result.children[1].attributes));
+    }
+
+    fn sorted(attrs: &std::collections::HashMap<String, String>) -> Vec<(&str, &str)> {
+        let mut v = attrs
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_namespace_uri() {
+        let fixture =
+            parse(r#"<a xmlns="urn:d" xmlns:p="urn:p"><p:b/><c xmlns=""/></a>"#.as_bytes())
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(Some("urn:d"), namespace_uri(&[&fixture]));
+        assert_eq!(
+            Some("urn:p"),
+            namespace_uri(&[&fixture, &fixture.children[0]])
+        );
+        assert_eq!(None, namespace_uri(&[&fixture, &fixture.children[1]]));
+    }
+}
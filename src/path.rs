@@ -0,0 +1,530 @@
+//! Path syntax shared by the `ElementExt` lookups.
+//!
+//! A path is a list of segments separated by `.` or `/`, each naming a child element
+//! or `*` for a child with any name,
+//! optionally followed by selectors: a zero-based positional index as in `result[1].name`,
+//! or an attribute predicate as in `task[@name="foo"]`. Predicate values may use single or
+//! double quotes, with `\` escaping the next character. Selectors apply left to right.
+//! An empty path refers to the element the lookup starts from.
+//!
+//! Outside selectors, `\` makes the next character part of the name, so `app\.version` names
+//! an `<app.version>` child rather than `<version>` inside `<app>`.
+
+use crate::UtilError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Selector {
+    Index(usize),
+    Attr { name: String, value: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Segment {
+    pub name: String,
+    pub selectors: Vec<Selector>,
+}
+
+impl Segment {
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name == "*" || self.name == name
+    }
+
+    /// Returns the positions of the children of `parent` selected by this segment, in document
+    /// order.
+    pub fn select_indices(&self, parent: &treexml::Element) -> Vec<usize> {
+        self.select_indices_by(parent, |c| self.matches_name(&c.name))
+    }
+
+    /// Like `select_indices`, with `matches` deciding which children the segment name accepts.
+    pub fn select_indices_by<M>(&self, parent: &treexml::Element, matches: M) -> Vec<usize>
+    where
+        M: Fn(&treexml::Element) -> bool,
+    {
+        let candidates = parent
+            .children
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| matches(c))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        self.filter_indices(parent, &candidates)
+    }
+
+    /// Applies the selectors to `candidates`, positions of children of `parent` that match the
+    /// segment name, in document order.
+    pub fn filter_indices(&self, parent: &treexml::Element, candidates: &[usize]) -> Vec<usize> {
+        let (mut candidates, selectors) = match self.selectors.split_first() {
+            Some((Selector::Index(n), rest)) => {
+                (candidates.get(*n).copied().into_iter().collect(), rest)
+            }
+            _ => (candidates.to_vec(), &self.selectors[..]),
+        };
+
+        for selector in selectors {
+            candidates = match selector {
+                Selector::Index(n) => candidates.get(*n).copied().into_iter().collect(),
+                Selector::Attr { name, value } => candidates
+                    .into_iter()
+                    .filter(|i| parent.children[*i].attributes.get(name) == Some(value))
+                    .collect(),
+            };
+        }
+
+        candidates
+    }
+
+    /// Checks whether a child seen while streaming is selected by this segment.
+    ///
+    /// `counters` holds one slot per selector and must be shared by all children of the same
+    /// parent, visited in document order.
+    pub fn accepts<'a, A>(&self, name: &str, attr: A, counters: &mut [usize]) -> bool
+    where
+        A: Fn(&str) -> Option<&'a str>,
+    {
+        if !self.matches_name(name) {
+            return false;
+        }
+
+        for (selector, counter) in self.selectors.iter().zip(counters) {
+            match selector {
+                Selector::Index(n) => {
+                    let position = *counter;
+                    *counter += 1;
+                    if position != *n {
+                        return false;
+                    }
+                }
+                Selector::Attr { name, value } => {
+                    if attr(name) != Some(value.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Returns the children of `parent` selected by this segment, in document order.
+    pub fn select<'a>(&self, parent: &'a treexml::Element) -> Vec<&'a treexml::Element> {
+        self.select_indices(parent)
+            .into_iter()
+            .map(|i| &parent.children[i])
+            .collect()
+    }
+}
+
+/// A path parsed ahead of time, for looking up the same path in many elements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    raw: String,
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    /// Validates and splits `path`, which uses the `ElementExt` syntax.
+    ///
+    /// Empty segments and malformed selectors are reported as `InvalidPath`.
+    pub fn parse(path: &str) -> Result<Self, UtilError> {
+        Ok(Self {
+            raw: path.into(),
+            segments: parse(path)?,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub(crate) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+impl std::str::FromStr for Path {
+    type Err = UtilError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+fn invalid_path(path: &str, reason: &str) -> UtilError {
+    UtilError::InvalidPath {
+        path: path.into(),
+        reason: reason.into(),
+    }
+}
+
+pub(crate) fn parse(path: &str) -> Result<Vec<Segment>, UtilError> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    split(path)?
+        .into_iter()
+        .map(|s| parse_segment(path, s))
+        .collect()
+}
+
+/// Splits `path` on separators that are not escaped or inside brackets or quotes.
+fn split(path: &str) -> Result<Vec<&str>, UtilError> {
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in path.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match c {
+            '\\' if depth == 0 => escaped = true,
+            '"' | '\'' if depth > 0 => quote = Some(c),
+            '[' => depth += 1,
+            ']' => {
+                if depth == 0 {
+                    return Err(invalid_path(path, "unbalanced brackets"));
+                }
+                depth -= 1;
+            }
+            '.' | '/' if depth == 0 => {
+                out.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if depth != 0 || quote.is_some() {
+        return Err(invalid_path(path, "unterminated selector"));
+    }
+    if escaped {
+        return Err(invalid_path(path, "trailing escape"));
+    }
+    out.push(&path[start..]);
+
+    Ok(out)
+}
+
+/// Escapes the characters of an element name that the path syntax would otherwise read as
+/// separators or selectors.
+pub(crate) fn escape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '.' | '/' | '[' | ']' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn parse_segment(path: &str, s: &str) -> Result<Segment, UtilError> {
+    let mut name = String::new();
+    let mut chars = s.char_indices();
+    let mut rest = "";
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => name.extend(chars.next().map(|(_, c)| c)),
+            '[' => {
+                rest = &s[i..];
+                break;
+            }
+            _ => name.push(c),
+        }
+    }
+
+    if name.is_empty() {
+        return Err(invalid_path(path, "empty segment"));
+    }
+
+    let mut selectors = Vec::new();
+    while !rest.is_empty() {
+        let end = selector_end(rest).ok_or_else(|| invalid_path(path, "malformed selector"))?;
+        selectors.push(parse_selector(path, &rest[1..end])?);
+        rest = &rest[end + 1..];
+    }
+
+    Ok(Segment { name, selectors })
+}
+
+/// Returns the position of the `]` closing the selector that `s` starts with.
+fn selector_end(s: &str) -> Option<usize> {
+    if !s.starts_with('[') {
+        return None;
+    }
+
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            ']' => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parse_selector(path: &str, s: &str) -> Result<Selector, UtilError> {
+    let s = s.trim();
+    let invalid = || invalid_path(path, &format!("invalid selector [{}]", s));
+
+    let predicate = match s.strip_prefix('@') {
+        Some(v) => v,
+        None => return s.parse().map(Selector::Index).map_err(|_| invalid()),
+    };
+
+    let (name, quoted) = predicate.split_once('=').ok_or_else(invalid)?;
+    let name = name.trim();
+    let quoted = quoted.trim();
+    if name.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut chars = quoted.chars();
+    let quote = match chars.next() {
+        Some(c @ '"') | Some(c @ '\'') => c,
+        _ => return Err(invalid()),
+    };
+
+    let mut value = String::new();
+    let mut closed = false;
+    while let Some(c) = chars.next() {
+        if closed {
+            return Err(invalid());
+        }
+        match c {
+            '\\' => value.push(chars.next().ok_or_else(invalid)?),
+            c if c == quote => closed = true,
+            c => value.push(c),
+        }
+    }
+    if !closed {
+        return Err(invalid());
+    }
+
+    Ok(Selector::Attr {
+        name: name.into(),
+        value,
+    })
+}
+
+/// Returns every element matching `segments`, in document order.
+pub(crate) fn resolve<'a>(
+    e: &'a treexml::Element,
+    segments: &[Segment],
+) -> Vec<&'a treexml::Element> {
+    let mut current = vec![e];
+    for segment in segments {
+        current = current
+            .into_iter()
+            .flat_map(|e| segment.select(e))
+            .collect();
+        if current.is_empty() {
+            break;
+        }
+    }
+
+    current
+}
+
+/// Returns the first element matching `segments`, in document order.
+pub(crate) fn resolve_first<'a>(
+    e: &'a treexml::Element,
+    segments: &[Segment],
+) -> Option<&'a treexml::Element> {
+    let (segment, rest) = match segments.split_first() {
+        Some(v) => v,
+        None => return Some(e),
+    };
+
+    if segment.selectors.is_empty() {
+        return e
+            .children
+            .iter()
+            .filter(|c| segment.matches_name(&c.name))
+            .find_map(|c| resolve_first(c, rest));
+    }
+
+    segment
+        .select_indices(e)
+        .into_iter()
+        .find_map(|i| resolve_first(&e.children[i], rest))
+}
+
+/// Returns the child positions leading to the first element matching `segments`.
+fn first_indices(e: &treexml::Element, segments: &[Segment]) -> Option<Vec<usize>> {
+    let (segment, rest) = match segments.split_first() {
+        Some(v) => v,
+        None => return Some(Vec::new()),
+    };
+
+    segment.select_indices(e).into_iter().find_map(|i| {
+        first_indices(&e.children[i], rest).map(|mut tail| {
+            tail.insert(0, i);
+            tail
+        })
+    })
+}
+
+/// Returns the first element matching `segments`, in document order.
+pub(crate) fn resolve_first_mut<'a>(
+    e: &'a mut treexml::Element,
+    segments: &[Segment],
+) -> Option<&'a mut treexml::Element> {
+    let mut current = e;
+    for i in first_indices(current, segments)? {
+        current = &mut current.children[i];
+    }
+
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let expectation = vec![
+            Segment {
+                name: "result".into(),
+                selectors: vec![Selector::Index(1)],
+            },
+            Segment {
+                name: "name".into(),
+                selectors: vec![],
+            },
+            Segment {
+                name: "*".into(),
+                selectors: vec![],
+            },
+        ];
+
+        let result = parse("result[1].name/*").unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_parse_predicate() {
+        let expectation = vec![Segment {
+            name: "task".into(),
+            selectors: vec![
+                Selector::Attr {
+                    name: "name".into(),
+                    value: "it's \"x.y\"".into(),
+                },
+                Selector::Index(0),
+            ],
+        }];
+
+        let result = parse(r#"task[@name='it\'s "x.y"'][0]"#).unwrap();
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_parse_escaped() {
+        let expectation = vec![
+            Segment {
+                name: "app.version".into(),
+                selectors: vec![Selector::Index(1)],
+            },
+            Segment {
+                name: "a/b\\c".into(),
+                selectors: vec![],
+            },
+        ];
+
+        let result = parse(r"app\.version[1]/a\/b\\c").unwrap();
+
+        assert_eq!(expectation, result);
+        assert_eq!(r"a\.b\/c\[0\]\\d", escape(r"a.b/c[0]\d"));
+        assert_eq!(
+            parse(&escape("app.version")).unwrap()[0].name,
+            "app.version"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for path in &[
+            "a..b",
+            ".a",
+            "a[",
+            "a[x]",
+            "a]",
+            "[0]",
+            "a[0]b",
+            "a[@x]",
+            "a[@x=y]",
+            "a[@=\"y\"]",
+            "a[@x=\"y\"z]",
+        ] {
+            assert!(parse(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_path_parse_errors() {
+        for (path, expectation) in &[
+            ("result..name", "empty segment"),
+            ("result.", "empty segment"),
+            ("task[@name=foo]", "invalid selector [@name=foo]"),
+            ("task[@name=\"foo\"", "unterminated selector"),
+            ("app\\", "trailing escape"),
+        ] {
+            let result = match Path::parse(path) {
+                Err(UtilError::InvalidPath { reason, .. }) => reason,
+                r => panic!("{}: {:?}", path, r),
+            };
+
+            assert_eq!(*expectation, result, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_path_parse() {
+        let result = Path::parse("result[1].name").unwrap();
+
+        assert_eq!("result[1].name", result.to_string());
+        assert_eq!(parse("result[1].name").unwrap(), result.segments());
+        assert_eq!(Vec::<Segment>::new(), Path::parse("").unwrap().segments());
+    }
+}
@@ -0,0 +1,219 @@
+//! Tree building with `quick-xml`, used by `parse_node` and `parse_node_from_reader` when the
+//! `quick-xml` feature is enabled.
+//!
+//! The tree matches the one `treexml` builds: names are split into prefix and local name,
+//! namespace declarations are dropped, whitespace-only text is dropped, text and CDATA are
+//! collected separately with the pieces of each concatenated, and comments and processing
+//! instructions are skipped. Only the predefined entities and character references are expanded.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::BufRead;
+
+/// Builds the tree for the document in `r`, or returns `None` if `quick-xml` rejects it or it does
+/// not have exactly one root element. The caller parses the document again with `treexml` in that
+/// case, so that errors are reported the same way with either backend, unless it was streamed
+/// and is too large to have been kept.
+pub(crate) fn parse<R>(r: R) -> Option<treexml::Element>
+where
+    R: BufRead,
+{
+    let mut reader = Reader::from_reader(r);
+    let mut buf = Vec::new();
+    let mut stack = Vec::<treexml::Element>::new();
+    let mut root = None;
+
+    loop {
+        match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(start) => {
+                if root.is_some() {
+                    return None;
+                }
+                stack.push(element(&start)?);
+            }
+            Event::Empty(start) => {
+                let e = element(&start)?;
+                close(&mut stack, &mut root, e)?;
+            }
+            Event::End(_) => {
+                let e = stack.pop()?;
+                close(&mut stack, &mut root, e)?;
+            }
+            Event::Text(text) => {
+                let text = text.unescape().ok()?;
+                if text.trim().is_empty() {
+                    // Whitespace-only text is ignored, as by `treexml`.
+                } else if let Some(e) = stack.last_mut() {
+                    e.text.get_or_insert_with(String::new).push_str(&text);
+                } else {
+                    return None;
+                }
+            }
+            Event::CData(cdata) => {
+                let cdata = cdata.decode().ok()?;
+                stack
+                    .last_mut()?
+                    .cdata
+                    .get_or_insert_with(String::new)
+                    .push_str(&cdata);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if stack.is_empty() {
+        root
+    } else {
+        None
+    }
+}
+
+fn element(start: &BytesStart) -> Option<treexml::Element> {
+    let name = std::str::from_utf8(start.name().into_inner()).ok()?;
+    let (prefix, name) = match name.split_once(':') {
+        Some((prefix, name)) => (Some(prefix.to_string()), name),
+        None => (None, name),
+    };
+    let mut e = treexml::Element {
+        prefix,
+        name: name.into(),
+        ..Default::default()
+    };
+
+    for attr in start.attributes() {
+        let attr = attr.ok()?;
+        let key = std::str::from_utf8(attr.key.into_inner()).ok()?;
+        if key == "xmlns" || key.starts_with("xmlns:") {
+            continue;
+        }
+        e.attributes
+            .insert(key.into(), attr.unescape_value().ok()?.into_owned());
+    }
+
+    Some(e)
+}
+
+/// Attaches the finished element `e` to its parent, or makes it the root.
+fn close(
+    stack: &mut [treexml::Element],
+    root: &mut Option<treexml::Element>,
+    e: treexml::Element,
+) -> Option<()> {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(e),
+        None if root.is_none() => *root = Some(e),
+        None => return None,
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize_element;
+    use std::collections::HashMap;
+
+    type Backend = (&'static str, fn(&str) -> Option<treexml::Element>);
+
+    /// Both backends, so each case checks that they agree.
+    fn backends() -> [Backend; 2] {
+        [
+            ("treexml", |s| {
+                treexml::Document::parse(s.as_bytes())
+                    .ok()
+                    .and_then(|doc| doc.root)
+            }),
+            ("quick-xml", |s| parse(s.as_bytes())),
+        ]
+    }
+
+    fn check(fixture: &str, expectation: Option<&treexml::Element>) {
+        for (backend, parse) in backends() {
+            assert_eq!(expectation, parse(fixture).as_ref(), "{}", backend);
+        }
+    }
+
+    #[test]
+    fn test_backends_entities() {
+        let fixture = r#"<a x="&lt;&#65;&quot;">&amp;&#x42;&gt;</a>"#;
+        let expectation = treexml::Element {
+            name: "a".into(),
+            attributes: HashMap::from([("x".to_string(), "<A\"".to_string())]),
+            text: Some("&B>".into()),
+            ..Default::default()
+        };
+
+        check(fixture, Some(&expectation));
+        check("<a>&nbsp;</a>", None);
+        check(r#"<a x="&bogus;"/>"#, None);
+    }
+
+    #[test]
+    fn test_backends_cdata() {
+        let fixture = "<a>one <![CDATA[<b>&amp;]]> two<![CDATA[!]]></a>";
+        let expectation = treexml::Element {
+            name: "a".into(),
+            text: Some("one  two".into()),
+            cdata: Some("<b>&amp;!".into()),
+            ..Default::default()
+        };
+
+        check(fixture, Some(&expectation));
+    }
+
+    #[test]
+    fn test_backends_attribute_order() {
+        let fixture = r#"<a z="1" a="2" m="3"/>"#;
+        let expectation = r#"<a a="2" m="3" z="1"/>"#;
+
+        for (backend, parse) in backends() {
+            let result = serialize_element(&parse(fixture).unwrap());
+            assert_eq!(expectation, result, "{}", backend);
+        }
+        check(r#"<a x="1" x="2"/>"#, None);
+    }
+
+    #[test]
+    fn test_backends_structure() {
+        let fixture = "<?xml version=\"1.0\"?>\n<!-- c --><s:root xmlns:s=\"urn:s\" s:k=\"v\">\n  <item>a<!-- c -->b</item>\n  <?pi x?><empty/>\n</s:root>\n";
+        let expectation = treexml::Element {
+            prefix: Some("s".into()),
+            name: "root".into(),
+            attributes: HashMap::from([("s:k".to_string(), "v".to_string())]),
+            children: vec![
+                treexml::Element {
+                    name: "item".into(),
+                    text: Some("ab".into()),
+                    ..Default::default()
+                },
+                treexml::Element::new("empty"),
+            ],
+            ..Default::default()
+        };
+
+        check(fixture, Some(&expectation));
+    }
+
+    #[test]
+    fn test_backends_malformed() {
+        for fixture in ["<a><b></a>", "<a>", "<a/><b/>", "<a/>text", "", "<a x=1/>"] {
+            check(fixture, None);
+        }
+    }
+
+    #[test]
+    fn test_rejected_documents_fall_back() {
+        let fixture = "<a>\n  <b></a>";
+
+        let expectation = crate::parse_node(fixture).unwrap_err();
+        assert_eq!(Some(2), expectation.line());
+
+        let result = crate::parse_node_from_reader(fixture.as_bytes()).unwrap_err();
+        assert_eq!(expectation.to_string(), result.to_string());
+
+        let result = crate::parse_node_from_reader("<a><b/></a>".as_bytes()).unwrap();
+        assert_eq!(parse("<a><b/></a>".as_bytes()), result);
+    }
+}
@@ -0,0 +1,351 @@
+//! Declarative description of the children an element may have, checked with `validate`.
+
+use crate::{check_value, UtilError, ValueKind};
+
+/// An element's expected children.
+///
+/// Children not listed are allowed unless `deny_unknown_children` is set. Attributes are not
+/// checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schema {
+    name: String,
+    children: Vec<Child>,
+    deny_unknown: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Child {
+    name: String,
+    occurs: Occurs,
+    kind: Kind,
+}
+
+/// What a child described by a `Schema` holds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    /// Any content, or none.
+    Text,
+    /// Content that parses as `i64`.
+    Int,
+    /// Content that parses as `f64`.
+    Float,
+    /// Content read by the `find_bool` rules.
+    Bool,
+    /// Children described by a nested schema, named after the child.
+    Element(Schema),
+}
+
+impl From<Schema> for Kind {
+    fn from(schema: Schema) -> Self {
+        Kind::Element(schema)
+    }
+}
+
+/// How many times a child described by a `Schema` may appear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Occurs {
+    /// Exactly once.
+    Once,
+    /// Once or not at all.
+    AtMostOnce,
+    /// Any number of times, including none.
+    Any,
+}
+
+impl std::fmt::Display for Occurs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Occurs::Once => "exactly one",
+            Occurs::AtMostOnce => "at most one",
+            Occurs::Any => "any number of",
+        })
+    }
+}
+
+impl Schema {
+    /// Describes an element named `name` with no children listed yet.
+    pub fn element(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            children: Vec::new(),
+            deny_unknown: false,
+        }
+    }
+
+    /// Adds a child that must appear exactly once.
+    ///
+    /// # Panics
+    ///
+    /// If `kind` is a nested schema for an element not named `name`. The same holds for
+    /// `optional_child` and `repeated_child`.
+    pub fn required_child<K>(self, name: &str, kind: K) -> Self
+    where
+        K: Into<Kind>,
+    {
+        self.child(name, Occurs::Once, kind.into())
+    }
+
+    /// Adds a child that may appear at most once.
+    pub fn optional_child<K>(self, name: &str, kind: K) -> Self
+    where
+        K: Into<Kind>,
+    {
+        self.child(name, Occurs::AtMostOnce, kind.into())
+    }
+
+    /// Adds a child that may appear any number of times.
+    pub fn repeated_child<K>(self, name: &str, kind: K) -> Self
+    where
+        K: Into<Kind>,
+    {
+        self.child(name, Occurs::Any, kind.into())
+    }
+
+    /// Reports children not listed in the schema as `UnknownChild`.
+    pub fn deny_unknown_children(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+
+    /// Returns the name of the element described.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn child(mut self, name: &str, occurs: Occurs, kind: Kind) -> Self {
+        if let Kind::Element(schema) = &kind {
+            assert!(
+                schema.name == name,
+                "schema for element {} given for child {}",
+                schema.name,
+                name
+            );
+        }
+        self.children.push(Child {
+            name: name.into(),
+            occurs,
+            kind,
+        });
+        self
+    }
+}
+
+/// A violation found by `validate`.
+///
+/// Paths use the `ElementExt` syntax relative to the validated element, with an index on segments
+/// whose element has siblings of the same name.
+#[derive(Debug, thiserror::Error)]
+pub enum ValidationError {
+    /// The validated element itself has the wrong name.
+    #[error("expected element {expected}, found {found}")]
+    WrongElement { expected: String, found: String },
+    /// A child not listed in a schema that denies unknown children.
+    #[error("unexpected child at {path}")]
+    UnknownChild { path: String },
+    /// A child appeared the wrong number of times. `path` names the child without an index.
+    #[error("expected {expected} {path}, found {found}")]
+    WrongCount {
+        path: String,
+        expected: Occurs,
+        found: usize,
+    },
+    /// A child's content does not parse as its `Kind`.
+    #[error(transparent)]
+    Value(UtilError),
+}
+
+impl ValidationError {
+    /// Returns the path of the element the violation is about.
+    pub fn path(&self) -> &str {
+        match self {
+            ValidationError::WrongElement { .. } => "",
+            ValidationError::UnknownChild { path } | ValidationError::WrongCount { path, .. } => {
+                path
+            }
+            ValidationError::Value(e) => e.path().unwrap_or(""),
+        }
+    }
+}
+
+/// Checks `e` against `schema`, returning every violation. The violations within each element
+/// come before the counts of its children being checked.
+pub fn validate(e: &treexml::Element, schema: &Schema) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if e.name != schema.name {
+        errors.push(ValidationError::WrongElement {
+            expected: schema.name.clone(),
+            found: e.name.clone(),
+        });
+    }
+    validate_children(e, schema, "", &mut errors);
+    errors
+}
+
+fn validate_children(
+    e: &treexml::Element,
+    schema: &Schema,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    let join = |name: &str| {
+        if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", path, name)
+        }
+    };
+
+    let mut seen = Vec::<(&str, usize)>::new();
+    for child in &e.children {
+        let total = e.children.iter().filter(|c| c.name == child.name).count();
+        let child_path = if total > 1 {
+            let n = match seen.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, n)) => {
+                    *n += 1;
+                    *n - 1
+                }
+                None => {
+                    seen.push((&child.name, 1));
+                    0
+                }
+            };
+            join(&format!("{}[{}]", child.name, n))
+        } else {
+            join(&child.name)
+        };
+
+        match schema.children.iter().find(|c| c.name == child.name) {
+            Some(spec) => match &spec.kind {
+                Kind::Element(schema) => validate_children(child, schema, &child_path, errors),
+                Kind::Text => {}
+                Kind::Int => check(child, &child_path, ValueKind::Int, errors),
+                Kind::Float => check(child, &child_path, ValueKind::Float, errors),
+                Kind::Bool => check(child, &child_path, ValueKind::Bool, errors),
+            },
+            None if schema.deny_unknown => {
+                errors.push(ValidationError::UnknownChild { path: child_path })
+            }
+            None => {}
+        }
+    }
+
+    for spec in &schema.children {
+        let found = e.children.iter().filter(|c| c.name == spec.name).count();
+        let ok = match spec.occurs {
+            Occurs::Once => found == 1,
+            Occurs::AtMostOnce => found <= 1,
+            Occurs::Any => true,
+        };
+        if !ok {
+            errors.push(ValidationError::WrongCount {
+                path: join(&spec.name),
+                expected: spec.occurs,
+                found,
+            });
+        }
+    }
+}
+
+fn check(e: &treexml::Element, path: &str, kind: ValueKind, errors: &mut Vec<ValidationError>) {
+    if let Err(e) = check_value(e, path, kind) {
+        errors.push(ValidationError::Value(e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    fn schema() -> Schema {
+        let app = Schema::element("app")
+            .required_child("name", Kind::Text)
+            .optional_child("non_cpu_intensive", Kind::Bool)
+            .deny_unknown_children();
+        Schema::element("project")
+            .required_child("master_url", Kind::Text)
+            .optional_child("resource_share", Kind::Float)
+            .optional_child("rpc_seqno", Kind::Int)
+            .repeated_child("app", app)
+    }
+
+    fn messages(errors: &[ValidationError]) -> Vec<String> {
+        errors.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let fixture = parse_node(
+            "<project><master_url>http://x/</master_url><resource_share> 100.5 </resource_share><app><name>a</name><non_cpu_intensive/></app><app><name>b</name></app><extra/></project>",
+        )
+        .unwrap()
+        .unwrap();
+
+        let result = validate(&fixture, &schema());
+
+        assert!(result.is_empty(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_validate_cardinality() {
+        let fixture = parse_node(
+            "<project><resource_share>1</resource_share><resource_share>2</resource_share><app/></project>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = vec![
+            "expected exactly one app.name, found 0",
+            "expected exactly one master_url, found 0",
+            "expected at most one resource_share, found 2",
+        ];
+
+        let result = validate(&fixture, &schema());
+
+        assert_eq!(expectation, messages(&result));
+        assert_eq!("app.name", result[0].path());
+    }
+
+    #[test]
+    fn test_validate_values() {
+        let fixture = parse_node(
+            "<project><master_url/><rpc_seqno>x</rpc_seqno><resource_share/><app><name>a</name><non_cpu_intensive>maybe</non_cpu_intensive></app></project>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = vec![
+            r#"invalid value "x" at rpc_seqno"#,
+            "value not found at resource_share",
+            r#"invalid boolean "maybe" at app.non_cpu_intensive"#,
+        ];
+
+        let result = validate(&fixture, &schema());
+
+        assert_eq!(expectation, messages(&result));
+        assert_eq!("app.non_cpu_intensive", result[2].path());
+    }
+
+    #[test]
+    #[should_panic(expected = "schema for element app given for child application")]
+    fn test_schema_nested_name_mismatch() {
+        Schema::element("project").repeated_child("application", Schema::element("app"));
+    }
+
+    #[test]
+    fn test_validate_unknown_children() {
+        let fixture = parse_node(
+            "<account><master_url>u</master_url><app><name>a</name><bogus/></app><app><name>b</name><bogus/><bogus/></app></account>",
+        )
+        .unwrap()
+        .unwrap();
+        let expectation = vec![
+            "expected element project, found account",
+            "unexpected child at app[0].bogus",
+            "unexpected child at app[1].bogus[0]",
+            "unexpected child at app[1].bogus[1]",
+        ];
+
+        let result = validate(&fixture, &schema());
+
+        assert_eq!(expectation, messages(&result));
+    }
+}
@@ -0,0 +1,679 @@
+//! Serialization of values into element trees with serde.
+
+use crate::{write, UtilError};
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt::Display;
+
+/// Serializes `value` as an element named `name`, following the conventions of `from_element`.
+///
+/// Struct fields and map entries become children named by their key, sequences become one child
+/// per item, and `None` produces no child at all. A key starting with `@` becomes an attribute,
+/// and the key `$text` becomes the text of the element, as do scalars and unit variants.
+/// Map keys must be strings, and `name` and the keys must be valid XML names. Enum variants with
+/// data and byte arrays are not supported.
+pub fn to_element<T>(name: &str, value: &T) -> Result<treexml::Element, UtilError>
+where
+    T: ?Sized + Serialize,
+{
+    if !write::is_xml_name(name) {
+        return Err(UtilError::InvalidName { name: name.into() });
+    }
+
+    let mut e = treexml::Element::new(name);
+    let value = value
+        .serialize(ValueSerializer)
+        .map_err(|err| err.within(name))?;
+    fill(&mut e, value).map_err(|err| err.within(name))?;
+    Ok(e)
+}
+
+impl ser::Error for UtilError {
+    fn custom<T: Display>(msg: T) -> Self {
+        UtilError::Custom {
+            path: String::new(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// What a value serializes to, before it is placed in an element.
+enum Value {
+    None,
+    Unit,
+    Text(String),
+    Seq(Vec<Value>),
+    Fields(Vec<(String, Value)>),
+}
+
+/// Writes `value` as the content of `e`.
+fn fill(e: &mut treexml::Element, value: Value) -> Result<(), UtilError> {
+    match value {
+        Value::None | Value::Unit => Ok(()),
+        Value::Text(text) => {
+            e.text = Some(text);
+            Ok(())
+        }
+        Value::Seq(_) => Err(unsupported("a sequence outside of a field")),
+        Value::Fields(fields) => fields
+            .into_iter()
+            .try_for_each(|(key, value)| add_field(e, &key, value)),
+    }
+}
+
+/// Writes `value` to `e` under `key`, as an attribute, the text or children.
+fn add_field(e: &mut treexml::Element, key: &str, value: Value) -> Result<(), UtilError> {
+    match (key, value) {
+        (_, Value::None) => Ok(()),
+        (_, Value::Seq(items)) => items
+            .into_iter()
+            .try_for_each(|item| add_field(e, key, item)),
+        ("$text", value) => fill(e, value),
+        (_, value) => {
+            if let Some(attr) = key.strip_prefix('@') {
+                if !write::is_xml_name(attr) {
+                    return Err(UtilError::InvalidName { name: attr.into() });
+                }
+                let text = match value {
+                    Value::Text(text) => text,
+                    Value::Unit => String::new(),
+                    _ => return Err(unsupported(&format!("a structure as attribute {}", attr))),
+                };
+                e.attributes.insert(attr.into(), text);
+                return Ok(());
+            }
+
+            if !write::is_xml_name(key) {
+                return Err(UtilError::InvalidName { name: key.into() });
+            }
+            let mut child = treexml::Element::new(key);
+            fill(&mut child, value).map_err(|err| err.within(key))?;
+            e.children.push(child);
+            Ok(())
+        }
+    }
+}
+
+fn unsupported(what: &str) -> UtilError {
+    ser::Error::custom(format_args!("cannot serialize {}", what))
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = UtilError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = Impossible<Value, UtilError>;
+    type SerializeMap = FieldsSerializer;
+    type SerializeStruct = FieldsSerializer;
+    type SerializeStructVariant = Impossible<Value, UtilError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, UtilError> {
+        Ok(Value::Text(v.into()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value, UtilError> {
+        Err(unsupported("a byte array"))
+    }
+
+    fn serialize_none(self) -> Result<Value, UtilError> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value, UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, UtilError> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, UtilError> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, UtilError> {
+        Ok(Value::Text(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Value, UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported(&format!("enum variant {} with data", variant)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, UtilError> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, UtilError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, UtilError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, UtilError> {
+        Err(unsupported(&format!("enum variant {} with data", variant)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<FieldsSerializer, UtilError> {
+        Ok(FieldsSerializer {
+            fields: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<FieldsSerializer, UtilError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, UtilError> {
+        Err(unsupported(&format!("enum variant {} with data", variant)))
+    }
+}
+
+struct SeqSerializer(Vec<Value>);
+
+impl SeqSerializer {
+    fn push<T>(&mut self, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = UtilError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, UtilError> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = UtilError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, UtilError> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = UtilError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, UtilError> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+struct FieldsSerializer {
+    fields: Vec<(String, Value)>,
+    /// The key of the map entry whose value comes next.
+    key: Option<String>,
+}
+
+impl FieldsSerializer {
+    fn push<T>(&mut self, key: String, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|err| err.within(&key))?;
+        self.fields.push((key, value));
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for FieldsSerializer {
+    type Ok = Value;
+    type Error = UtilError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.key.take().expect("value serialized before its key");
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value, UtilError> {
+        Ok(Value::Fields(self.fields))
+    }
+}
+
+impl ser::SerializeStruct for FieldsSerializer {
+    type Ok = Value;
+    type Error = UtilError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(key.into(), value)
+    }
+
+    fn end(self) -> Result<Value, UtilError> {
+        Ok(Value::Fields(self.fields))
+    }
+}
+
+/// Accepts only strings, which are all that can name an element or attribute.
+struct KeySerializer;
+
+fn non_string_key() -> UtilError {
+    ser::Error::custom("map keys must be strings")
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = UtilError;
+    type SerializeSeq = Impossible<String, UtilError>;
+    type SerializeTuple = Impossible<String, UtilError>;
+    type SerializeTupleStruct = Impossible<String, UtilError>;
+    type SerializeTupleVariant = Impossible<String, UtilError>;
+    type SerializeMap = Impossible<String, UtilError>;
+    type SerializeStruct = Impossible<String, UtilError>;
+    type SerializeStructVariant = Impossible<String, UtilError>;
+
+    fn serialize_str(self, v: &str) -> Result<String, UtilError> {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, UtilError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, UtilError> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_none(self) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<String, UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(non_string_key())
+    }
+
+    fn serialize_unit(self) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, UtilError>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(non_string_key())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, UtilError> {
+        Err(non_string_key())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, UtilError> {
+        Err(non_string_key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_element, parse_node, serialize_element};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Project {
+        #[serde(rename = "@id")]
+        id: u32,
+        name: String,
+        owner: Owner,
+        #[serde(default)]
+        task: Vec<Task>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deadline: Option<String>,
+        score: Score,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Owner {
+        name: String,
+        active: bool,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Task {
+        #[serde(rename = "@state")]
+        state: State,
+        #[serde(rename = "$text")]
+        title: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum State {
+        #[serde(rename = "open")]
+        Open,
+        #[serde(rename = "done")]
+        Done,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Score(f64);
+
+    fn fixture() -> Project {
+        Project {
+            id: 7,
+            name: "Boinc & co".into(),
+            owner: Owner {
+                name: "vorot93".into(),
+                active: false,
+            },
+            task: vec![
+                Task {
+                    state: State::Open,
+                    title: "Port".into(),
+                },
+                Task {
+                    state: State::Done,
+                    title: "Test".into(),
+                },
+            ],
+            deadline: None,
+            score: Score(0.5),
+        }
+    }
+
+    #[test]
+    fn test_to_element() {
+        let expectation = r#"<project id="7"><name>Boinc &amp; co</name><owner><name>vorot93</name><active>false</active></owner><task state="open">Port</task><task state="done">Test</task><score>0.5</score></project>"#;
+
+        let result = serialize_element(&to_element("project", &fixture()).unwrap());
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_to_element_round_trip() {
+        let mut expectation = fixture();
+        for deadline in [None, Some("2024-01-01".to_string())] {
+            expectation.deadline = deadline;
+
+            let xml = serialize_element(&to_element("project", &expectation).unwrap());
+            let result = from_element::<Project>(&parse_node(&xml).unwrap().unwrap()).unwrap();
+
+            assert_eq!(expectation, result);
+        }
+    }
+
+    #[test]
+    fn test_to_element_map() {
+        let fixture = BTreeMap::from([("a", 1), ("b", 2)]);
+
+        let result = serialize_element(&to_element("m", &fixture).unwrap());
+
+        assert_eq!("<m><a>1</a><b>2</b></m>", result);
+    }
+
+    #[test]
+    fn test_to_element_errors() {
+        let fixture = HashMap::from([(1, "a")]);
+        let e = to_element("m", &fixture).unwrap_err();
+        assert!(matches!(e, UtilError::Custom { path, .. } if path == "m"));
+
+        let fixture = HashMap::from([("not a name", "a")]);
+        let e = to_element("m", &fixture).unwrap_err();
+        assert!(matches!(e, UtilError::InvalidName { name } if name == "not a name"));
+
+        let e = to_element("m", &vec![1, 2]).unwrap_err();
+        assert!(matches!(e, UtilError::Custom { .. }));
+
+        let e = to_element("1m", &HashMap::from([("a", "b")])).unwrap_err();
+        assert!(matches!(e, UtilError::InvalidName { name } if name == "1m"));
+    }
+}
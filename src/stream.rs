@@ -0,0 +1,146 @@
+//! Value extraction from a streamed document, without building the tree.
+
+use crate::path::{self, Segment};
+use crate::UtilError;
+use std::collections::HashMap;
+use xml::reader::{EventReader, XmlEvent};
+
+/// State of an element on the path from the root to the current position.
+struct Open {
+    /// For each path, whether this element matches the path's first `depth` segments.
+    matched: Vec<bool>,
+    /// For each path, the selector counters shared by this element's children.
+    counters: Vec<Vec<usize>>,
+    /// Whether some path ends at this element, so its content is collected.
+    complete: bool,
+    text: Option<String>,
+    cdata: Option<String>,
+}
+
+/// Collects the content of the elements matching each of `paths` while streaming `r`.
+///
+/// Paths are relative to the root element and use the `ElementExt` syntax. The result has an entry
+/// for every path, with values in document order; elements without text or cdata are skipped, as in
+/// `find_value_all`. Only the elements enclosing the current position are kept in memory.
+pub fn extract_values<R>(r: R, paths: &[&str]) -> Result<HashMap<String, Vec<String>>, UtilError>
+where
+    R: std::io::Read,
+{
+    let segments = paths
+        .iter()
+        .map(|p| path::parse(p))
+        .collect::<Result<Vec<_>, UtilError>>()?;
+    let mut out = paths
+        .iter()
+        .map(|p| (p.to_string(), Vec::new()))
+        .collect::<HashMap<_, _>>();
+
+    let mut stack = Vec::<Open>::new();
+    for event in EventReader::new(r) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let depth = stack.len();
+                let attr = |n: &str| {
+                    attributes
+                        .iter()
+                        .find(|a| a.name.local_name == n)
+                        .map(|a| a.value.as_str())
+                };
+
+                let matched = match stack.last_mut() {
+                    None => vec![true; paths.len()],
+                    Some(parent) => segments
+                        .iter()
+                        .enumerate()
+                        .map(|(i, segs)| {
+                            parent.matched[i]
+                                && segs.get(depth - 1).is_some_and(|s: &Segment| {
+                                    s.accepts(&name.local_name, attr, &mut parent.counters[i])
+                                })
+                        })
+                        .collect(),
+                };
+                let counters = segments
+                    .iter()
+                    .zip(&matched)
+                    .map(|(segs, m)| match segs.get(depth) {
+                        Some(s) if *m => vec![0; s.selectors.len()],
+                        _ => Vec::new(),
+                    })
+                    .collect();
+                let complete = segments
+                    .iter()
+                    .zip(&matched)
+                    .any(|(segs, m)| *m && segs.len() == depth);
+
+                stack.push(Open {
+                    matched,
+                    counters,
+                    complete,
+                    text: None,
+                    cdata: None,
+                });
+            }
+            XmlEvent::Characters(s) => {
+                if let Some(open) = stack.last_mut().filter(|e| e.complete) {
+                    open.text.get_or_insert_with(String::new).push_str(&s);
+                }
+            }
+            XmlEvent::CData(s) => {
+                if let Some(open) = stack.last_mut().filter(|e| e.complete) {
+                    open.cdata.get_or_insert_with(String::new).push_str(&s);
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                let depth = stack.len() - 1;
+                let open = match stack.pop() {
+                    Some(open) => open,
+                    None => continue,
+                };
+                let value = match open.text.or(open.cdata) {
+                    Some(value) if open.complete => value,
+                    _ => continue,
+                };
+
+                for (i, segs) in segments.iter().enumerate() {
+                    if open.matched[i] && segs.len() == depth {
+                        if let Some(values) = out.get_mut(paths[i]) {
+                            values.push(value.clone());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_node, ElementExt};
+
+    #[test]
+    fn test_extract_values_matches_find_value_all() {
+        let fixture = r#"<stats><user id="1"><name>a</name><credit>1.5</credit></user><team><name>t</name></team><user id="2"><name>b</name><credit><![CDATA[2.5]]></credit></user><user id="3"><name/></user></stats>"#;
+        let paths = ["user.name", "user[@id='2'].credit", "*[1].name", "user[1]"];
+
+        let result = extract_values(fixture.as_bytes(), &paths).unwrap();
+
+        let root = parse_node(fixture).unwrap().unwrap();
+        for p in &paths {
+            let expectation = root.find_value_all::<String, _>(*p).unwrap();
+            assert_eq!(expectation, result[*p], "{}", p);
+        }
+    }
+
+    #[test]
+    fn test_extract_values_errors() {
+        assert!(extract_values("<a><b></a>".as_bytes(), &["b"]).is_err());
+        assert!(extract_values("<a/>".as_bytes(), &["b["]).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Support for `assert_xml_eq!`.
+
+use crate::{
+    deep_eq, diff_elements, parse_node, serialize_element, strip_whitespace_text, EqOptions,
+};
+use std::borrow::Cow;
+
+/// An argument to `assert_xml_eq!`: an element, or a string parsed with `parse_node`.
+pub trait AsXml {
+    /// Returns the element, panicking if a string is not a well-formed document with a root.
+    fn as_xml(&self) -> Cow<'_, treexml::Element>;
+}
+
+impl AsXml for treexml::Element {
+    fn as_xml(&self) -> Cow<'_, treexml::Element> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsXml for str {
+    fn as_xml(&self) -> Cow<'_, treexml::Element> {
+        match parse_node(self) {
+            Ok(Some(e)) => Cow::Owned(e),
+            Ok(None) => panic!("assert_xml_eq!: no root element in {:?}", self),
+            Err(e) => panic!("assert_xml_eq!: invalid XML {:?}: {}", self, e),
+        }
+    }
+}
+
+impl AsXml for String {
+    fn as_xml(&self) -> Cow<'_, treexml::Element> {
+        self.as_str().as_xml()
+    }
+}
+
+impl<T> AsXml for &T
+where
+    T: AsXml + ?Sized,
+{
+    fn as_xml(&self) -> Cow<'_, treexml::Element> {
+        (**self).as_xml()
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_xml_eq<A, E>(actual: &A, expected: &E, opts: &EqOptions)
+where
+    A: AsXml + ?Sized,
+    E: AsXml + ?Sized,
+{
+    let (actual, expected) = (actual.as_xml(), expected.as_xml());
+    if deep_eq(&actual, &expected, opts) {
+        return;
+    }
+
+    // Apply the relaxations the diff can, so that it does not list what `deep_eq` ignored.
+    let normalize = |e: &treexml::Element| {
+        let mut e = e.clone();
+        if opts.trim_text {
+            strip_whitespace_text(&mut e);
+        }
+        if opts.ignore_attributes {
+            clear_attributes(&mut e);
+        }
+        e
+    };
+    let diffs = diff_elements(&normalize(&actual), &normalize(&expected));
+
+    let mut report = String::from("XML trees differ:\n");
+    if actual.name != expected.name || actual.prefix != expected.prefix {
+        report.push_str(&format!(
+            "  <root>: element {} != {}\n",
+            actual.name, expected.name
+        ));
+    }
+    for diff in diffs {
+        report.push_str(&format!("  {}\n", diff));
+    }
+    report.push_str(&format!(
+        "actual:   {}\nexpected: {}",
+        serialize_element(&actual),
+        serialize_element(&expected)
+    ));
+    panic!("{}", report);
+}
+
+fn clear_attributes(e: &mut treexml::Element) {
+    e.attributes.clear();
+    for child in &mut e.children {
+        clear_attributes(child);
+    }
+}
+
+/// Asserts that two trees are equal, comparing with `deep_eq`, and panics with the
+/// `diff_elements` report otherwise.
+///
+/// Each side is a `treexml::Element` or a string parsed with `parse_node`. Text is trimmed unless
+/// an `EqOptions` is passed as a third argument. With `ignore_child_order`, the report still pairs
+/// children by position and may list differences that do not count.
+///
+/// ```
+/// # use treexml_util::{assert_xml_eq, element};
+/// let e = element!("project" { "name": "p" });
+/// assert_xml_eq!(e, "<project>\n  <name>p</name>\n</project>");
+/// ```
+#[macro_export]
+macro_rules! assert_xml_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_xml_eq!(
+            $actual,
+            $expected,
+            $crate::EqOptions {
+                trim_text: true,
+                ..::std::default::Default::default()
+            }
+        )
+    };
+    ($actual:expr, $expected:expr, $opts:expr $(,)?) => {
+        $crate::test_util::assert_xml_eq(&$actual, &$expected, &$opts)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{element, EqOptions};
+
+    #[test]
+    fn test_assert_xml_eq_passes() {
+        let e = element!("project" ["id" = 1] { "name": "p", "app" {} });
+
+        assert_xml_eq!(e, r#"<project id="1"> <name> p </name><app/></project>"#);
+        assert_xml_eq!(&e, e.clone());
+        assert_xml_eq!("<a><b/></a>", String::from("<a>\n  <b/>\n</a>"));
+        assert_xml_eq!(
+            "<a><b/><c/></a>",
+            "<a><c/><b/></a>",
+            EqOptions {
+                ignore_child_order: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "result[1].name: text \"b\" != \"c\"")]
+    fn test_assert_xml_eq_reports_path() {
+        let e = element!("reply" { "result" { "name": "a" }, "result" { "name": "b" } });
+
+        assert_xml_eq!(
+            e,
+            "<reply><result><name>a</name></result><result><name>c</name></result></reply>"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "<root>: element a != b")]
+    fn test_assert_xml_eq_root_name() {
+        assert_xml_eq!("<a/>", "<b/>");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid XML")]
+    fn test_assert_xml_eq_invalid() {
+        assert_xml_eq!("<a>", "<a/>");
+    }
+}
@@ -0,0 +1,205 @@
+//! Depth-first traversal with callbacks on entering and leaving each element.
+
+use std::collections::HashMap;
+
+/// What `walk` does after `Visitor::enter` returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisitAction {
+    Continue,
+    /// Leave out the children of this element. `exit` is still called for it.
+    SkipChildren,
+    /// End the walk without calling any more callbacks.
+    Stop,
+}
+
+/// Callbacks for `walk`. `path` is relative to the element the walk started from, in the
+/// `ElementExt` syntax with an index on segments whose element has siblings of the same name, e.g.
+/// `result[1].name`. The starting element's path is empty.
+pub trait Visitor<E> {
+    fn enter(&mut self, path: &str, e: &treexml::Element) -> Result<VisitAction, E>;
+
+    /// Called after the children of `e` have been walked.
+    fn exit(&mut self, _path: &str, _e: &treexml::Element) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+/// Walks `e` and its descendants in document order, stopping at the first error.
+pub fn walk<E, V>(e: &treexml::Element, visitor: &mut V) -> Result<(), E>
+where
+    V: Visitor<E>,
+{
+    walk_at(&mut String::new(), e, visitor).map(|_| ())
+}
+
+/// Walks `e`, found at `path`, returning `false` if the visitor stopped the walk. `path` is
+/// restored before returning.
+fn walk_at<E, V>(path: &mut String, e: &treexml::Element, visitor: &mut V) -> Result<bool, E>
+where
+    V: Visitor<E>,
+{
+    match visitor.enter(path, e)? {
+        VisitAction::Stop => return Ok(false),
+        VisitAction::SkipChildren => {}
+        VisitAction::Continue => {
+            let mut totals = HashMap::<&str, usize>::new();
+            for child in &e.children {
+                *totals.entry(&child.name).or_default() += 1;
+            }
+
+            let mut seen = HashMap::<&str, usize>::new();
+            let len = path.len();
+            for child in &e.children {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&child.name);
+                if totals[child.name.as_str()] > 1 {
+                    let n = seen.entry(&child.name).or_default();
+                    path.push_str(&format!("[{}]", n));
+                    *n += 1;
+                }
+
+                let go_on = walk_at(path, child, visitor)?;
+                path.truncate(len);
+                if !go_on {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    visitor.exit(path, e)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    /// Records every callback, applying `actions` to the paths they name.
+    #[derive(Default)]
+    struct Recorder {
+        actions: Vec<(&'static str, VisitAction)>,
+        fail_at: Option<&'static str>,
+        log: Vec<String>,
+    }
+
+    impl Visitor<String> for Recorder {
+        fn enter(&mut self, path: &str, e: &treexml::Element) -> Result<VisitAction, String> {
+            if self.fail_at == Some(path) {
+                return Err(format!("bad {} at {}", e.name, path));
+            }
+            self.log.push(format!("enter {}", path));
+            Ok(self
+                .actions
+                .iter()
+                .find(|(p, _)| *p == path)
+                .map_or(VisitAction::Continue, |(_, a)| *a))
+        }
+
+        fn exit(&mut self, path: &str, _e: &treexml::Element) -> Result<(), String> {
+            self.log.push(format!("exit {}", path));
+            Ok(())
+        }
+    }
+
+    fn fixture() -> treexml::Element {
+        parse_node("<reply><result><name/></result><result><name/><file/></result><app/></reply>")
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_walk_paths() {
+        let fixture = fixture();
+        let expectation = vec![
+            "enter ",
+            "enter result[0]",
+            "enter result[0].name",
+            "exit result[0].name",
+            "exit result[0]",
+            "enter result[1]",
+            "enter result[1].name",
+            "exit result[1].name",
+            "enter result[1].file",
+            "exit result[1].file",
+            "exit result[1]",
+            "enter app",
+            "exit app",
+            "exit ",
+        ];
+
+        let mut result = Recorder::default();
+        walk(&fixture, &mut result).unwrap();
+
+        assert_eq!(expectation, result.log);
+    }
+
+    #[test]
+    fn test_walk_skip_children() {
+        let fixture = fixture();
+        let expectation = vec![
+            "enter ",
+            "enter result[0]",
+            "exit result[0]",
+            "enter result[1]",
+            "enter result[1].name",
+            "exit result[1].name",
+            "enter result[1].file",
+            "exit result[1].file",
+            "exit result[1]",
+            "enter app",
+            "exit app",
+            "exit ",
+        ];
+
+        let mut result = Recorder {
+            actions: vec![("result[0]", VisitAction::SkipChildren)],
+            ..Default::default()
+        };
+        walk(&fixture, &mut result).unwrap();
+
+        assert_eq!(expectation, result.log);
+    }
+
+    #[test]
+    fn test_walk_stop() {
+        let fixture = fixture();
+        let expectation = vec![
+            "enter ",
+            "enter result[0]",
+            "enter result[0].name",
+            "exit result[0].name",
+            "exit result[0]",
+            "enter result[1]",
+            "enter result[1].name",
+        ];
+
+        let mut result = Recorder {
+            actions: vec![("result[1].name", VisitAction::Stop)],
+            ..Default::default()
+        };
+        walk(&fixture, &mut result).unwrap();
+
+        assert_eq!(expectation, result.log);
+    }
+
+    #[test]
+    fn test_walk_error() {
+        let fixture = fixture();
+
+        let mut visitor = Recorder {
+            fail_at: Some("result[1].file"),
+            ..Default::default()
+        };
+        let e = walk(&fixture, &mut visitor).unwrap_err();
+
+        assert_eq!("bad file at result[1].file", e);
+        assert_eq!(
+            Some("exit result[1].name"),
+            visitor.log.last().map(String::as_str)
+        );
+    }
+}
@@ -0,0 +1,595 @@
+//! Serialization of element trees.
+
+use crate::UtilError;
+use std::io;
+
+/// Layout used by `serialize_pretty`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Inserted once per nesting level, e.g. two spaces or a tab.
+    pub indent: String,
+    pub newline: String,
+    /// Keep elements that only hold text or cdata on one line, as in `<name>foo</name>`.
+    pub inline_text: bool,
+    /// Put each attribute on its own line when an opening tag would exceed this many characters.
+    pub max_width: Option<usize>,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        Self {
+            indent: "  ".into(),
+            newline: "\n".into(),
+            inline_text: true,
+            max_width: None,
+        }
+    }
+}
+
+/// The `<?xml ...?>` prologue written before the root element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Declaration {
+    pub version: String,
+    /// Only UTF-8 can be declared, since output is never transcoded.
+    pub encoding: Option<String>,
+    /// Written as `standalone="yes"` or `standalone="no"` when set.
+    pub standalone: Option<bool>,
+}
+
+impl Default for Declaration {
+    fn default() -> Self {
+        Self {
+            version: "1.0".into(),
+            encoding: Some("UTF-8".into()),
+            standalone: None,
+        }
+    }
+}
+
+/// Controls the output of `write_element`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Prologue to write before the element; `None` writes none.
+    pub declaration: Option<Declaration>,
+    /// Indent the output; `None` writes everything on one line.
+    pub pretty: Option<PrettyOptions>,
+    /// Drop whitespace-only text.
+    pub compact: bool,
+    /// Drop attributes with an empty value.
+    pub skip_empty_attributes: bool,
+}
+
+/// Serializes `e` as XML without a declaration or added whitespace.
+///
+/// Attributes are written sorted by key, and elements without content are written as `<x/>`.
+/// Namespace declarations are not tracked by `treexml`, so prefixed names are written as-is.
+pub fn serialize_element(e: &treexml::Element) -> String {
+    to_string(e, &WriteOptions::default())
+}
+
+/// Serializes `e` as indented XML without a declaration.
+///
+/// Text placed on its own line picks up the surrounding indentation, so the output parses back to
+/// the original tree only up to trimming of text.
+pub fn serialize_pretty(e: &treexml::Element, opts: &PrettyOptions) -> String {
+    to_string(
+        e,
+        &WriteOptions {
+            pretty: Some(opts.clone()),
+            ..Default::default()
+        },
+    )
+}
+
+/// Serializes `e` in as few bytes as possible, without a declaration.
+///
+/// Whitespace-only text is dropped, and with `skip_empty_attributes` so are attributes with an
+/// empty value.
+pub fn serialize_compact(e: &treexml::Element, skip_empty_attributes: bool) -> String {
+    to_string(
+        e,
+        &WriteOptions {
+            compact: true,
+            skip_empty_attributes,
+            ..Default::default()
+        },
+    )
+}
+
+/// Like `serialize_element`, preceded by `declaration` unless it is `None`.
+///
+/// Declaring an encoding other than UTF-8 is an error.
+pub fn serialize_document(
+    e: &treexml::Element,
+    declaration: Option<&Declaration>,
+) -> Result<String, UtilError> {
+    let opts = WriteOptions {
+        declaration: declaration.cloned(),
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    write_element(&mut out, e, &opts)?;
+    Ok(String::from_utf8(out).expect("serialized XML is UTF-8"))
+}
+
+/// Streams `e` to `w`, without building the output in memory.
+///
+/// Produces the same bytes as the `serialize_*` functions for the same options. Fails with
+/// `UnsupportedEncoding` before writing anything if the declaration names an encoding other than
+/// UTF-8, and with `Write` if `w` does.
+pub fn write_element<W>(
+    w: &mut W,
+    e: &treexml::Element,
+    opts: &WriteOptions,
+) -> Result<(), UtilError>
+where
+    W: io::Write,
+{
+    if let Some(encoding) = opts.declaration.as_ref().and_then(unsupported_encoding) {
+        return Err(UtilError::UnsupportedEncoding {
+            encoding: encoding.into(),
+        });
+    }
+
+    let write = |w: &mut W| -> io::Result<()> {
+        if let Some(declaration) = &opts.declaration {
+            write_declaration(w, declaration)?;
+            let newline = opts.pretty.as_ref().map_or("\n", |pretty| &pretty.newline);
+            w.write_all(newline.as_bytes())?;
+        }
+        write_node(w, e, opts, 0)
+    };
+    write(w).map_err(|source| UtilError::Write { source })
+}
+
+/// Returns the encoding named by `declaration` if it is not UTF-8, which is all the writer
+/// produces.
+fn unsupported_encoding(declaration: &Declaration) -> Option<&str> {
+    declaration
+        .encoding
+        .as_deref()
+        .filter(|encoding| !encoding.eq_ignore_ascii_case("utf-8"))
+}
+
+fn write_declaration<W>(w: &mut W, declaration: &Declaration) -> io::Result<()>
+where
+    W: io::Write,
+{
+    write!(w, "<?xml version=\"{}\"", declaration.version)?;
+    if let Some(encoding) = &declaration.encoding {
+        write!(w, " encoding=\"{}\"", encoding)?;
+    }
+    if let Some(standalone) = declaration.standalone {
+        write!(
+            w,
+            " standalone=\"{}\"",
+            if standalone { "yes" } else { "no" }
+        )?;
+    }
+    w.write_all(b"?>")
+}
+
+fn to_string(e: &treexml::Element, opts: &WriteOptions) -> String {
+    let mut out = Vec::new();
+    write_node(&mut out, e, opts, 0).expect("writing to a Vec cannot fail");
+    String::from_utf8(out).expect("serialized XML is valid UTF-8")
+}
+
+fn write_node<W>(
+    out: &mut W,
+    e: &treexml::Element,
+    opts: &WriteOptions,
+    depth: usize,
+) -> io::Result<()>
+where
+    W: io::Write,
+{
+    let pretty = opts.pretty.as_ref();
+    let name = match &e.prefix {
+        Some(prefix) => format!("{}:{}", prefix, e.name),
+        None => e.name.clone(),
+    };
+
+    let mut attributes = e
+        .attributes
+        .iter()
+        .filter(|(_, v)| !opts.skip_empty_attributes || !v.is_empty())
+        .collect::<Vec<_>>();
+    attributes.sort();
+    let attributes = attributes
+        .into_iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v, true)))
+        .collect::<Vec<_>>();
+
+    let text = e
+        .text
+        .as_deref()
+        .filter(|v| !v.is_empty() && (!opts.compact || !v.trim().is_empty()));
+    let cdata = e.cdata.as_deref().filter(|v| !v.is_empty());
+    let empty = text.is_none() && cdata.is_none() && e.children.is_empty();
+
+    let line = |out: &mut W, depth: usize| -> io::Result<()> {
+        if let Some(pretty) = pretty {
+            out.write_all(pretty.newline.as_bytes())?;
+            for _ in 0..depth {
+                out.write_all(pretty.indent.as_bytes())?;
+            }
+        }
+        Ok(())
+    };
+
+    write!(out, "<{}", name)?;
+    let width = pretty.map_or(0, |pretty| pretty.indent.len() * depth)
+        + name.len()
+        + attributes.iter().map(|a| a.len() + 1).sum::<usize>()
+        + 3;
+    let wrap = pretty
+        .and_then(|pretty| pretty.max_width)
+        .is_some_and(|max| attributes.len() > 1 && width > max);
+    for attribute in &attributes {
+        if wrap {
+            line(out, depth + 1)?;
+        } else {
+            out.write_all(b" ")?;
+        }
+        out.write_all(attribute.as_bytes())?;
+    }
+
+    if empty {
+        return out.write_all(b"/>");
+    }
+    out.write_all(b">")?;
+
+    let inline = e.children.is_empty() && pretty.map_or(true, |pretty| pretty.inline_text);
+    if let Some(text) = text {
+        if !inline {
+            line(out, depth + 1)?;
+        }
+        out.write_all(escape(text, false).as_bytes())?;
+    }
+    if let Some(cdata) = cdata {
+        if !inline {
+            line(out, depth + 1)?;
+        }
+        write!(out, "<![CDATA[{}]]>", escape_cdata(cdata))?;
+    }
+    for child in &e.children {
+        line(out, depth + 1)?;
+        write_node(out, child, opts, depth + 1)?;
+    }
+    if !inline {
+        line(out, depth)?;
+    }
+    write!(out, "</{}>", name)
+}
+
+/// Checks whether `c` may appear in an XML 1.0 document.
+pub(crate) fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && c != '\u{fffe}' && c != '\u{ffff}')
+}
+
+/// Checks whether `s` is a valid XML 1.0 element or attribute name.
+pub(crate) fn is_xml_name(s: &str) -> bool {
+    fn is_start(c: char) -> bool {
+        matches!(c,
+            ':' | 'A'..='Z' | '_' | 'a'..='z' | '\u{c0}'..='\u{d6}' | '\u{d8}'..='\u{f6}'
+            | '\u{f8}'..='\u{2ff}' | '\u{370}'..='\u{37d}' | '\u{37f}'..='\u{1fff}'
+            | '\u{200c}'..='\u{200d}' | '\u{2070}'..='\u{218f}' | '\u{2c00}'..='\u{2fef}'
+            | '\u{3001}'..='\u{d7ff}' | '\u{f900}'..='\u{fdcf}' | '\u{fdf0}'..='\u{fffd}'
+            | '\u{10000}'..='\u{effff}')
+    }
+    fn is_rest(c: char) -> bool {
+        is_start(c)
+            || matches!(c,
+                '-' | '.' | '0'..='9' | '\u{b7}' | '\u{300}'..='\u{36f}' | '\u{203f}'..='\u{2040}')
+    }
+
+    let mut chars = s.chars();
+    chars.next().is_some_and(is_start) && chars.all(is_rest)
+}
+
+/// Splits `]]>` across two CDATA sections and drops characters XML 1.0 cannot represent.
+fn escape_cdata(s: &str) -> String {
+    s.chars()
+        .filter(|c| is_xml_char(*c))
+        .collect::<String>()
+        .replace("]]>", "]]]]><![CDATA[>")
+}
+
+/// Escapes markup characters and drops characters XML 1.0 cannot represent. In attributes,
+/// whitespace other than spaces is escaped too, since parsers normalize it to spaces.
+fn escape(s: &str, attribute: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars().filter(|c| is_xml_char(*c)) {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            '\n' if attribute => out.push_str("&#10;"),
+            '\r' if attribute => out.push_str("&#13;"),
+            '\t' if attribute => out.push_str("&#9;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{make_cdata_element, make_text_element, make_tree_element, parse_node};
+
+    #[test]
+    fn test_serialize_escaping() {
+        let mut fixture = make_tree_element(
+            "project",
+            vec![
+                make_text_element("name", "R&D <lab>"),
+                make_cdata_element("note", "<b>&</b>"),
+                treexml::Element::new("empty"),
+            ],
+        );
+        fixture
+            .attributes
+            .insert("title".into(), r#"say "hi" & <go>"#.into());
+        let expectation = r#"<project title="say &quot;hi&quot; &amp; &lt;go&gt;"><name>R&amp;D &lt;lab&gt;</name><note><![CDATA[<b>&</b>]]></note><empty/></project>"#;
+
+        let result = serialize_element(&fixture);
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_serialize_attribute_whitespace() {
+        let mut fixture = treexml::Element::new("task");
+        fixture
+            .attributes
+            .insert("note".into(), "a\nb\r\nc\td  e".into());
+        let expectation = r#"<task note="a&#10;b&#13;&#10;c&#9;d  e"/>"#;
+
+        let result = serialize_element(&fixture);
+        assert_eq!(expectation, result);
+
+        let result = parse_node(&result).unwrap().unwrap();
+        assert_eq!(fixture, result);
+    }
+
+    #[test]
+    fn test_serialize_roundtrip() {
+        let mut fixture = make_tree_element(
+            "scheduler_request",
+            vec![
+                make_text_element("authenticator", "a&b"),
+                make_tree_element(
+                    "host_info",
+                    vec![
+                        make_text_element("p_ncpus", 4),
+                        make_text_element("domain_name", "caf\u{e9} <1>"),
+                    ],
+                ),
+                make_cdata_element("global_preferences", "<mod_time>1</mod_time>"),
+            ],
+        );
+        fixture
+            .attributes
+            .insert("version".into(), "'7' \"x\"".into());
+
+        let result =
+            parse_node(&serialize_document(&fixture, Some(&Declaration::default())).unwrap())
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(fixture, result);
+    }
+
+    #[test]
+    fn test_serialize_document() {
+        let fixture = make_text_element("status", "ok");
+
+        for (declaration, expectation) in &[
+            (
+                Some(Declaration::default()),
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<status>ok</status>",
+            ),
+            (None, "<status>ok</status>"),
+            (
+                Some(Declaration {
+                    standalone: Some(true),
+                    ..Default::default()
+                }),
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<status>ok</status>",
+            ),
+            (
+                Some(Declaration {
+                    version: "1.1".into(),
+                    encoding: None,
+                    standalone: Some(false),
+                }),
+                "<?xml version=\"1.1\" standalone=\"no\"?>\n<status>ok</status>",
+            ),
+        ] {
+            let result = serialize_document(&fixture, declaration.as_ref()).unwrap();
+
+            assert_eq!(*expectation, result);
+        }
+    }
+
+    #[test]
+    fn test_serialize_document_encoding() {
+        let fixture = make_text_element("status", "ok");
+        let declaration = Declaration {
+            encoding: Some("ISO-8859-1".into()),
+            ..Default::default()
+        };
+
+        let result = serialize_document(&fixture, Some(&declaration)).unwrap_err();
+
+        assert!(
+            matches!(&result, UtilError::UnsupportedEncoding { encoding } if encoding == "ISO-8859-1"),
+            "{}",
+            result
+        );
+        assert!(serialize_document(
+            &fixture,
+            Some(&Declaration {
+                encoding: Some("utf-8".into()),
+                ..Default::default()
+            })
+        )
+        .is_ok());
+    }
+
+    fn pretty_fixture() -> treexml::Element {
+        let mut project = make_tree_element(
+            "project",
+            vec![
+                make_text_element("name", "Example"),
+                make_cdata_element("note", "x"),
+                treexml::Element::new("suspended"),
+            ],
+        );
+        project
+            .attributes
+            .insert("url".into(), "https://example.com/".into());
+        project.attributes.insert("id".into(), "1".into());
+
+        make_tree_element("account", vec![project])
+    }
+
+    #[test]
+    fn test_serialize_pretty() {
+        let expectation = "<account>\n  <project id=\"1\" url=\"https://example.com/\">\n    <name>Example</name>\n    <note><![CDATA[x]]></note>\n    <suspended/>\n  </project>\n</account>";
+
+        let result = serialize_pretty(&pretty_fixture(), &PrettyOptions::default());
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_serialize_pretty_tabs() {
+        let opts = PrettyOptions {
+            indent: "\t".into(),
+            newline: "\r\n".into(),
+            inline_text: false,
+            max_width: Some(20),
+        };
+        let expectation = "<account>\r\n\t<project\r\n\t\tid=\"1\"\r\n\t\turl=\"https://example.com/\">\r\n\t\t<name>\r\n\t\t\tExample\r\n\t\t</name>\r\n\t\t<note>\r\n\t\t\t<![CDATA[x]]>\r\n\t\t</note>\r\n\t\t<suspended/>\r\n\t</project>\r\n</account>";
+
+        let result = serialize_pretty(&pretty_fixture(), &opts);
+
+        assert_eq!(expectation, result);
+    }
+
+    #[test]
+    fn test_serialize_pretty_roundtrip() {
+        let fixture = pretty_fixture();
+        let eq = crate::EqOptions {
+            trim_text: true,
+            ..Default::default()
+        };
+
+        for opts in &[
+            PrettyOptions::default(),
+            PrettyOptions {
+                inline_text: false,
+                max_width: Some(10),
+                ..Default::default()
+            },
+        ] {
+            let result = parse_node(&serialize_pretty(&fixture, opts))
+                .unwrap()
+                .unwrap();
+
+            assert!(crate::deep_eq(&fixture, &result, &eq));
+        }
+    }
+
+    #[test]
+    fn test_serialize_compact() {
+        let mut fixture = pretty_fixture();
+        fixture.children[0]
+            .attributes
+            .insert("group".into(), "".into());
+        let expectation = r#"<account><project id="1" url="https://example.com/"><name>Example</name><note><![CDATA[x]]></note><suspended/></project></account>"#;
+
+        let result = serialize_compact(&fixture, true);
+        assert_eq!(expectation, result);
+        assert!(result.len() < serialize_pretty(&fixture, &PrettyOptions::default()).len());
+
+        let result = parse_node(&serialize_compact(&fixture, false))
+            .unwrap()
+            .unwrap();
+        assert_eq!(fixture, result);
+    }
+
+    #[test]
+    fn test_write_element() {
+        let fixture = pretty_fixture();
+        let opts = WriteOptions {
+            pretty: Some(PrettyOptions::default()),
+            ..Default::default()
+        };
+        let expectation = serialize_pretty(&fixture, &PrettyOptions::default());
+
+        let mut result = Vec::new();
+        write_element(&mut result, &fixture, &opts).unwrap();
+
+        assert_eq!(expectation.as_bytes(), &result[..]);
+    }
+
+    #[test]
+    fn test_write_element_error() {
+        struct Limited(usize);
+
+        impl io::Write for Limited {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "sink full"));
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let fixture = pretty_fixture();
+
+        let result = write_element(&mut Limited(30), &fixture, &WriteOptions::default());
+
+        assert!(matches!(
+            result,
+            Err(UtilError::Write { source }) if source.kind() == io::ErrorKind::WriteZero
+        ));
+        assert!(write_element(&mut Limited(1024), &fixture, &WriteOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_serialize_cdata_terminator() {
+        let fixture = make_cdata_element("script", "if (a[b[0]]>1) {}]]>");
+        let expectation =
+            "<script><![CDATA[if (a[b[0]]]]><![CDATA[>1) {}]]]]><![CDATA[>]]></script>";
+
+        let result = serialize_element(&fixture);
+        assert_eq!(expectation, result);
+
+        let result = parse_node(&result).unwrap().unwrap();
+        assert_eq!(fixture.cdata, result.cdata);
+    }
+
+    #[test]
+    fn test_serialize_strips_illegal_chars() {
+        let mut fixture = make_text_element("name", "a\u{0}b\u{1b}c\td");
+        fixture.attributes.insert("x".into(), "\u{ffff}1".into());
+        fixture.cdata = Some("e\u{8}f".into());
+        let expectation = "<name x=\"1\">abc\td<![CDATA[ef]]></name>";
+
+        let result = serialize_element(&fixture);
+
+        assert_eq!(expectation, result);
+    }
+}
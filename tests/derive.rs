@@ -0,0 +1,71 @@
+#![cfg(feature = "derive")]
+
+use treexml_util::{parse_node, Unmarshaller, UtilError};
+
+#[derive(Debug, Default, PartialEq, Unmarshaller)]
+struct App {
+    name: String,
+    #[treexml(rename = "user_friendly_name")]
+    title: String,
+    #[treexml(default)]
+    version: i64,
+}
+
+#[derive(Debug, Default, PartialEq, Unmarshaller)]
+struct Project {
+    master_url: String,
+    resource_share: Option<f64>,
+    suspended_via_gui: bool,
+    app: App,
+    #[treexml(skip)]
+    local_note: String,
+}
+
+#[test]
+fn test_derive_nested() {
+    let fixture = parse_node(
+        "<project>\
+         <master_url>http://example.com/</master_url>\
+         <suspended_via_gui/>\
+         <app><name>setiathome</name><user_friendly_name>SETI@home</user_friendly_name></app>\
+         <local_note>ignored</local_note>\
+         </project>",
+    )
+    .unwrap()
+    .unwrap();
+    let expectation = Project {
+        master_url: "http://example.com/".into(),
+        resource_share: None,
+        suspended_via_gui: true,
+        app: App {
+            name: "setiathome".into(),
+            title: "SETI@home".into(),
+            version: 0,
+        },
+        local_note: "untouched".into(),
+    };
+
+    let mut result = Project {
+        local_note: "untouched".into(),
+        ..Default::default()
+    };
+    assert!(result.unmarshal_from(&fixture).unwrap());
+
+    assert_eq!(expectation, result);
+}
+
+#[test]
+fn test_derive_missing_required() {
+    let fixture = parse_node("<app><name>setiathome</name></app>")
+        .unwrap()
+        .unwrap();
+
+    let mut result = App::default();
+    let e = result.unmarshal_from(&fixture).unwrap_err();
+
+    assert!(matches!(
+        e,
+        UtilError::MissingField { field, element, parent }
+            if field == "title" && element == "user_friendly_name" && parent == "app"
+    ));
+}
@@ -0,0 +1,84 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(current, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Generates `<stats><user><id>0</id><credit>..</credit></user>...<total>n</total></stats>` on the fly.
+struct Generated {
+    users: usize,
+    next: usize,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Generated {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.buf.clear();
+            self.pos = 0;
+            if self.next == 0 {
+                self.buf.extend_from_slice(b"<stats>");
+            }
+            if self.next < self.users {
+                let user = format!(
+                    "<user><id>{}</id><credit>{}.5</credit><host><name>h{}</name></host></user>",
+                    self.next, self.next, self.next
+                );
+                self.buf.extend_from_slice(user.as_bytes());
+            } else if self.next == self.users {
+                let total = format!("<total>{}</total></stats>", self.users);
+                self.buf.extend_from_slice(total.as_bytes());
+            }
+            self.next += 1;
+        }
+
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_extract_values_streaming() {
+    let fixture = Generated {
+        users: 50_000,
+        next: 0,
+        buf: Vec::new(),
+        pos: 0,
+    };
+
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let result =
+        treexml_util::extract_values(fixture, &["total", "user[49999].id", "user[7].host.name"])
+            .unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - baseline;
+
+    assert_eq!(vec!["50000".to_string()], result["total"]);
+    assert_eq!(vec!["49999".to_string()], result["user[49999].id"]);
+    assert_eq!(vec!["h7".to_string()], result["user[7].host.name"]);
+    // The generated document is several megabytes; the extraction should only ever hold a few
+    // elements and the reader's buffers.
+    assert!(peak < 256 * 1024, "peak allocation: {} bytes", peak);
+}